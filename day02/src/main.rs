@@ -1,31 +1,27 @@
-use intcode::{load_intcode, Computer};
+use intcode::{load_intcode, Computer, Patch, Solver, Word};
 
-fn run_intcode_patched(intcode: &[i64], patch: (i64, i64)) -> Computer {
+fn run_intcode_patched(intcode: &[Word], patch: &Patch) -> Computer {
     let mut computer = Computer::new(intcode, &[]);
     computer.patch(patch);
     computer.run().unwrap();
     computer
 }
 
-fn find_correct_patch(intcode: &[i64], target: i64) -> Option<(i64, i64)> {
-    for x in 0..100 {
-        for y in 0..100 {
-            let patch = (x, y);
-            let computer = run_intcode_patched(intcode, patch);
-            if computer.memory()[0] == target {
-                return Some(patch);
-            }
-        }
-    }
-    None
+/// Solves for the noun and verb directly when the program allows it, and searches otherwise.
+fn find_correct_patch(intcode: &[Word], target: Word) -> Option<(Word, Word)> {
+    let patch = Solver::new(intcode)
+        .unknown(1, 0..=99)
+        .unknown(2, 0..=99)
+        .solve(target)?;
+    Some((patch.get(1)?, patch.get(2)?))
 }
 
-fn part1(intcode: &[i64]) -> i64 {
-    let computer = run_intcode_patched(intcode, (12, 2));
+fn part1(intcode: &[Word]) -> Word {
+    let computer = run_intcode_patched(intcode, &Patch::noun_verb(12, 2));
     computer.memory()[0]
 }
 
-fn part2(intcode: &[i64]) -> i64 {
+fn part2(intcode: &[Word]) -> Word {
     match find_correct_patch(intcode, 19_690_720) {
         Some((x, y)) => 100 * x + y,
         None => 0,
@@ -62,16 +58,18 @@ mod tests {
     #[test]
     fn test_examples_part2() {
         let intcode = load_intcode("input/input.txt");
-        let computer = run_intcode_patched(&intcode, (12, 2));
-        let (x, y) = computer.get_patch();
-        assert_eq!(100 * x + y, 1202);
+        let patch: Patch = "1=12,2=2".parse().unwrap();
+        let computer = run_intcode_patched(&intcode, &patch);
+        let changed = Patch::diff(&intcode, computer.memory());
+        assert_eq!((changed.get(1), changed.get(2)), (Some(12), Some(2)));
+        assert_eq!(changed.get(0), Some(3_654_868));
     }
 
     #[test]
     fn test_part1() {
         let intcode = load_intcode("input/input.txt");
         assert_eq!(
-            run_intcode_patched(&intcode, (12, 2)).memory()[0],
+            run_intcode_patched(&intcode, &Patch::noun_verb(12, 2)).memory()[0],
             3_654_868
         );
     }
@@ -81,5 +79,11 @@ mod tests {
         let intcode = load_intcode("input/input.txt");
         let (x, y) = find_correct_patch(&intcode, 19_690_720).unwrap();
         assert_eq!(100 * x + y, 7014);
+
+        let brute_force = Solver::new(&intcode)
+            .unknown(1, 0..=99)
+            .unknown(2, 0..=99)
+            .brute_force(19_690_720);
+        assert_eq!(brute_force, Some(Patch::noun_verb(x, y)));
     }
 }
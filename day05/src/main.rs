@@ -1,30 +1,43 @@
-use intcode::{load_intcode, run_intcode};
+use intcode_macros::transpile_intcode;
+
+transpile_intcode!(diagnostic, "input/input.txt");
 
 fn main() {
-    let intcode = load_intcode("input/input.txt");
     println!(
         "Part 1: {}",
-        run_intcode(&intcode, &[1]).last_output().unwrap()
+        diagnostic(&[1]).unwrap().last_output().unwrap()
     );
     println!(
         "Part 2: {}",
-        run_intcode(&intcode, &[5]).last_output().unwrap()
+        diagnostic(&[5]).unwrap().last_output().unwrap()
     );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use intcode::{run_intcode, Word};
+    use intcode_macros::include_intcode;
+
+    const INTCODE: &[Word] = &include_intcode!("input/input.txt");
 
     #[test]
     fn test_solution_part1() {
-        let intcode = load_intcode("input/input.txt");
-        assert_eq!(run_intcode(&intcode, &[1]).last_output(), Some(12_440_243));
+        assert_eq!(run_intcode(INTCODE, &[1]).last_output(), Some(12_440_243));
     }
 
     #[test]
     fn test_solution_part2() {
-        let intcode = load_intcode("input/input.txt");
-        assert_eq!(run_intcode(&intcode, &[5]).last_output(), Some(15_486_302));
+        assert_eq!(run_intcode(INTCODE, &[5]).last_output(), Some(15_486_302));
+    }
+
+    #[test]
+    fn test_transpiled() {
+        for &input in &[1, 5] {
+            let transpiled = diagnostic(&[input]).unwrap();
+            let interpreted = run_intcode(INTCODE, &[input]);
+            assert_eq!(transpiled.outputs(), interpreted.outputs());
+            assert_eq!(transpiled.snapshot(), interpreted.snapshot());
+        }
     }
 }
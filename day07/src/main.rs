@@ -1,4 +1,4 @@
-use intcode::{load_intcode, run_intcode, Computer};
+use intcode::{load_intcode, run_intcode, Pipeline};
 use itertools::Itertools;
 
 // .fold(0, |i, s| Computer::new(code, &[s, i]).run())
@@ -14,24 +14,22 @@ fn find_best_sequence(code: &[i64]) -> i64 {
 }
 
 fn find_best_sequence_feedback(code: &[i64]) -> i64 {
-    (0..5)
+    (5..10)
         .permutations(5)
         .map(|p| {
-            let mut computers = p
+            let mut pipeline = Pipeline::new();
+            let ids = p
                 .into_iter()
-                .map(|x| Computer::new(code, &[x + 5]))
-                .collect::<Vec<Computer>>();
-
-            let mut input = 0;
-            while !computers[4].is_halted() {
-                for computer in computers.iter_mut() {
-                    computer.push_input(input);
-                    computer.step().unwrap();
-                    input = computer.last_output().unwrap();
-                }
-            }
+                .enumerate()
+                .map(|(i, x)| {
+                    let inputs = if i == 0 { vec![x, 0] } else { vec![x] };
+                    pipeline.add(code, &inputs)
+                })
+                .collect::<Vec<usize>>();
+            pipeline.ring(&ids);
 
-            computers[4].last_output().unwrap()
+            let finished = pipeline.spawn().join();
+            *finished[4].outputs().last().unwrap()
         })
         .max()
         .unwrap()
@@ -1,50 +1,74 @@
-use intcode::{load_intcode, run_intcode};
+use intcode_macros::transpile_intcode;
+
+transpile_intcode!(boost, "input/input.txt");
 
 fn main() {
-    let intcode = load_intcode("input/input.txt");
-    println!(
-        "Part 1: {}",
-        run_intcode(&intcode, &[1]).last_output().unwrap()
-    );
-    println!(
-        "Part 2: {}",
-        run_intcode(&intcode, &[2]).last_output().unwrap()
-    );
+    println!("Part 1: {}", boost(&[1]).unwrap().last_output().unwrap());
+    println!("Part 2: {}", boost(&[2]).unwrap().last_output().unwrap());
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use intcode::{run_intcode, Arithmetic, Computer, Word};
+    use intcode_macros::include_intcode;
+
+    const INTCODE: &[Word] = &include_intcode!("input/input.txt");
+    const POLICIES: &[Arithmetic] = &[Arithmetic::Checked, Arithmetic::Wrapping];
+
+    fn run_with(intcode: &[Word], inputs: &[Word], arithmetic: Arithmetic) -> Computer {
+        let mut computer = Computer::new(intcode, inputs);
+        computer.set_arithmetic(arithmetic);
+        computer.run().unwrap();
+        computer
+    }
 
     #[test]
     fn test_examples_part1() {
-        let intcode = &[
-            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
-        ];
-        let computer = run_intcode(intcode, &[]);
-        assert_eq!(computer.outputs(), intcode);
-
-        let intcode = &[1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
-        let last_output = run_intcode(intcode, &[]).last_output().unwrap();
-        assert_eq!(last_output.to_string().len(), 16);
-
-        let intcode = &[104, 1_125_899_906_842_624, 99];
-        let last_output = run_intcode(intcode, &[]).last_output().unwrap();
-        assert_eq!(last_output, intcode[1]);
+        for &arithmetic in POLICIES {
+            let intcode = &[
+                109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+            ];
+            let computer = run_with(intcode, &[], arithmetic);
+            assert_eq!(computer.outputs(), intcode);
+
+            let intcode = &[1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
+            let last_output = run_with(intcode, &[], arithmetic).last_output().unwrap();
+            assert_eq!(last_output.to_string().len(), 16);
+
+            let intcode = &[104, 1_125_899_906_842_624, 99];
+            let last_output = run_with(intcode, &[], arithmetic).last_output().unwrap();
+            assert_eq!(last_output, intcode[1]);
+        }
     }
 
     #[test]
     fn test_solution_part1() {
-        let intcode = load_intcode("input/input.txt");
-        assert_eq!(
-            run_intcode(&intcode, &[1]).last_output(),
-            Some(2_316_632_620)
-        );
+        for &arithmetic in POLICIES {
+            assert_eq!(
+                run_with(INTCODE, &[1], arithmetic).last_output(),
+                Some(2_316_632_620)
+            );
+        }
     }
 
     #[test]
     fn test_solution_part2() {
-        let intcode = load_intcode("input/input.txt");
-        assert_eq!(run_intcode(&intcode, &[2]).last_output(), Some(78869));
+        for &arithmetic in POLICIES {
+            assert_eq!(
+                run_with(INTCODE, &[2], arithmetic).last_output(),
+                Some(78869)
+            );
+        }
+    }
+
+    #[test]
+    fn test_transpiled() {
+        for &input in &[1, 2] {
+            let transpiled = boost(&[input]).unwrap();
+            let interpreted = run_intcode(INTCODE, &[input]);
+            assert_eq!(transpiled.outputs(), interpreted.outputs());
+            assert_eq!(transpiled.snapshot(), interpreted.snapshot());
+        }
     }
 }
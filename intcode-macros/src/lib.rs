@@ -0,0 +1,85 @@
+//! Compile time loading of Intcode programs.
+//!
+//! ```ignore
+//! const PROGRAM: &[Word] = &include_intcode!("input/input.txt");
+//! transpile_intcode!(run_program, "input/input.txt");
+//! ```
+//!
+//! Paths are relative to the manifest directory of the crate using the macro. Files are read
+//! with `intcode::try_load_intcode`, so comments and binary programs work too, and a malformed
+//! file is a compile error pointing at the offending token.
+
+extern crate proc_macro;
+
+use intcode::Word;
+use proc_macro::{TokenStream, TokenTree};
+use std::env;
+use std::path::PathBuf;
+
+/// Expands to the program in the given file as an `[intcode::Word; N]` array expression.
+#[proc_macro]
+pub fn include_intcode(input: TokenStream) -> TokenStream {
+    expand(input, false, |_, path, program| {
+        let words = program.iter().map(|w| w.to_string()).collect::<Vec<_>>();
+        format!(
+            "{{ {} let words: [::intcode::Word; {}] = [{}]; words }}",
+            track(path),
+            words.len(),
+            words.join(", ")
+        )
+    })
+}
+
+/// `transpile_intcode!(name, "path")` defines `fn name(inputs: &[Word]) -> Result<Computer,
+/// IntcodeError>` that runs the program as specialized Rust code, see `intcode::transpile`.
+#[proc_macro]
+pub fn transpile_intcode(input: TokenStream) -> TokenStream {
+    expand(input, true, |name, path, program| {
+        format!("{}\n{}", track(path), intcode::transpile(program, name))
+    })
+}
+
+/// Makes cargo rebuild when the file changes.
+fn track(path: &str) -> String {
+    format!("const _: &[u8] = include_bytes!({:?});", path)
+}
+
+fn expand<F>(input: TokenStream, named: bool, generate: F) -> TokenStream
+where
+    F: Fn(&str, &str, &[Word]) -> String,
+{
+    let result = arguments(input, named).and_then(|(name, path)| {
+        let full = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap_or_default()).join(&path);
+        let full = full.to_string_lossy().into_owned();
+        let program = intcode::try_load_intcode(&full).map_err(|e| format!("{}: {}", full, e))?;
+        Ok(generate(&name, &full, &program))
+    });
+    match result {
+        Ok(source) => source.parse().unwrap(),
+        Err(message) => format!("compile_error!({:?});", message).parse().unwrap(),
+    }
+}
+
+/// `name, "path"` if `named`, otherwise just `"path"` with an empty name.
+fn arguments(input: TokenStream, named: bool) -> Result<(String, String), String> {
+    let tokens: Vec<TokenTree> = input.into_iter().collect();
+    let (name, literal) = match (&tokens[..], named) {
+        ([TokenTree::Literal(literal)], false) => (String::new(), literal),
+        ([TokenTree::Ident(name), TokenTree::Punct(comma), TokenTree::Literal(literal)], true)
+            if comma.as_char() == ',' =>
+        {
+            (name.to_string(), literal)
+        }
+        (_, false) => return Err("expected a path to an Intcode program".to_string()),
+        (_, true) => return Err("expected `fn_name, \"path\"`".to_string()),
+    };
+
+    let literal = literal.to_string();
+    match literal.strip_prefix('"').and_then(|l| l.strip_suffix('"')) {
+        Some(path) if !path.contains('\\') => Ok((name, path.to_string())),
+        _ => Err(format!(
+            "expected a plain string literal, found {}",
+            literal
+        )),
+    }
+}
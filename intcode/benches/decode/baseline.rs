@@ -0,0 +1,222 @@
+//! The interpreter loop as it was before the decode cache and the rest of the typed
+//! `Computer`, kept as the reference the benchmark measures against. Only what running a
+//! program needs is kept, with `Word` in place of `i64`.
+
+use intcode::Word;
+use std::convert::{TryFrom, TryInto};
+
+enum Op {
+    Add,
+    Multiply,
+    Read,
+    Write,
+    JumpIfTrue,
+    JumpIfFalse,
+    LessThan,
+    Equals,
+    AdjustBase,
+    Halt,
+}
+
+impl TryFrom<Word> for Op {
+    type Error = String;
+
+    fn try_from(n: Word) -> Result<Self, Self::Error> {
+        match n {
+            1 => Ok(Self::Add),
+            2 => Ok(Self::Multiply),
+            3 => Ok(Self::Read),
+            4 => Ok(Self::Write),
+            5 => Ok(Self::JumpIfTrue),
+            6 => Ok(Self::JumpIfFalse),
+            7 => Ok(Self::LessThan),
+            8 => Ok(Self::Equals),
+            9 => Ok(Self::AdjustBase),
+            99 => Ok(Self::Halt),
+            _ => Err(format!("illegal operation code: {}", n)),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Position,
+    Immediate,
+    Relative,
+}
+
+impl TryFrom<Word> for Mode {
+    type Error = String;
+
+    fn try_from(n: Word) -> Result<Self, Self::Error> {
+        match n {
+            0 => Ok(Self::Position),
+            1 => Ok(Self::Immediate),
+            2 => Ok(Self::Relative),
+            _ => Err(format!("illegal argument mode: {}", n)),
+        }
+    }
+}
+
+struct Instruction {
+    op: Op,
+    modes: [Mode; 3],
+}
+
+impl TryFrom<Word> for Instruction {
+    type Error = String;
+
+    fn try_from(n: Word) -> Result<Self, Self::Error> {
+        let op = (n % 100).try_into()?;
+        let mut n = n / 100;
+        let mut modes = [Mode::Position; 3];
+
+        for mode in modes.iter_mut() {
+            *mode = (n % 10).try_into()?;
+            n /= 10;
+        }
+
+        Ok(Self { op, modes })
+    }
+}
+
+#[derive(Default)]
+pub struct Computer {
+    memory: Vec<Word>,
+    inputs: Vec<Word>,
+    outputs: Vec<Word>,
+    ip: Word,
+    halted: bool,
+    base: Word,
+}
+
+impl Computer {
+    pub fn new(intcode: &[Word], inputs: &[Word]) -> Self {
+        Self {
+            memory: intcode.to_vec(),
+            inputs: inputs.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    pub fn outputs(&self) -> &[Word] {
+        &self.outputs
+    }
+
+    pub fn patch(&mut self, patch: (Word, Word)) {
+        self.memory[1] = patch.0;
+        self.memory[2] = patch.1;
+    }
+
+    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        while !self.halted {
+            self.step()?;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        loop {
+            let instr = Instruction::try_from(self.memory[usize::try_from(self.ip)?])?;
+            match instr.op {
+                Op::Add => {
+                    let x = self.arg(1, instr.modes[0]);
+                    let y = self.arg(2, instr.modes[1]);
+                    self.put(3, x + y, instr.modes[2]);
+                    self.ip += 4;
+                }
+                Op::Multiply => {
+                    let x = self.arg(1, instr.modes[0]);
+                    let y = self.arg(2, instr.modes[1]);
+                    self.put(3, x * y, instr.modes[2]);
+                    self.ip += 4;
+                }
+                Op::Read => {
+                    let input = self.inputs.remove(0);
+                    self.put(1, input, instr.modes[0]);
+                    self.ip += 2;
+                }
+                Op::Write => {
+                    let x = self.arg(1, instr.modes[0]);
+                    self.outputs.push(x);
+                    self.ip += 2;
+                    break;
+                }
+                Op::JumpIfTrue => {
+                    if self.arg(1, instr.modes[0]) == 0 {
+                        self.ip += 3;
+                    } else {
+                        self.ip = self.arg(2, instr.modes[1]);
+                    }
+                }
+                Op::JumpIfFalse => {
+                    if self.arg(1, instr.modes[0]) == 0 {
+                        self.ip = self.arg(2, instr.modes[1]);
+                    } else {
+                        self.ip += 3;
+                    }
+                }
+                Op::LessThan => {
+                    if self.arg(1, instr.modes[0]) < self.arg(2, instr.modes[1]) {
+                        self.put(3, 1, instr.modes[2]);
+                    } else {
+                        self.put(3, 0, instr.modes[2]);
+                    }
+                    self.ip += 4;
+                }
+                Op::Equals => {
+                    if self.arg(1, instr.modes[0]) == self.arg(2, instr.modes[1]) {
+                        self.put(3, 1, instr.modes[2]);
+                    } else {
+                        self.put(3, 0, instr.modes[2]);
+                    }
+                    self.ip += 4;
+                }
+                Op::AdjustBase => {
+                    self.base += self.arg(1, instr.modes[0]);
+                    self.ip += 2;
+                }
+                Op::Halt => {
+                    self.halted = true;
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn arg(&mut self, arg_index: Word, mode: Mode) -> Word {
+        let value = self.memory_get(self.ip + arg_index);
+        match mode {
+            Mode::Position => self.memory_get(value),
+            Mode::Immediate => value,
+            Mode::Relative => self.memory_get(self.base + value),
+        }
+    }
+
+    fn put(&mut self, index: Word, value: Word, mode: Mode) {
+        let dest = self.memory_get(self.ip + index);
+        match mode {
+            Mode::Position => self.memory_set(dest, value),
+            Mode::Immediate => panic!("immediate mode illegal for storing values"),
+            Mode::Relative => self.memory_set(self.base + dest, value),
+        };
+    }
+
+    fn memory_get(&mut self, index: Word) -> Word {
+        let index: usize = index.try_into().unwrap();
+        if self.memory.len() < index + 1 {
+            self.memory.resize(index + 1, 0);
+        }
+        self.memory[index]
+    }
+
+    fn memory_set(&mut self, index: Word, value: Word) {
+        let index: usize = index.try_into().unwrap();
+        if self.memory.len() < index + 1 {
+            self.memory.resize(index + 1, 0);
+        }
+        self.memory[index] = value;
+    }
+}
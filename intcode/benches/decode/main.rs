@@ -0,0 +1,114 @@
+//! Compares the interpreter with and without the decode cache against the original loop in
+//! `baseline`.
+//!
+//! Run with `cargo bench`; pass a number to change the rounds. On one core, 50 rounds:
+//!
+//! ```text
+//! day02 part 1       baseline  713.00ns  uncached  589.00ns (1.21x)  cached  559.00ns (1.28x)
+//! day02 brute force  baseline    7.63ms  uncached    5.65ms (1.35x)  cached    5.78ms (1.32x)
+//! day09 part 2       baseline    7.20ms  uncached    6.55ms (1.10x)  cached    3.35ms (2.15x)
+//! ```
+//!
+//! Without limits, recording or extensions the interpreter runs a loop that leaves out the
+//! checks for them. Short runs like day 2 end before the cache fills, so both modes run
+//! alike there; on long runs like day 9 the cache about halves the time.
+
+mod baseline;
+
+use intcode::{parse_intcode, Computer, Patch, Status, Word};
+use std::env;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy)]
+enum Interpreter {
+    Baseline,
+    Uncached,
+    Cached,
+}
+
+fn time<F: FnMut()>(repetitions: u32, mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..repetitions {
+        f();
+    }
+    start.elapsed() / repetitions
+}
+
+/// Runs to completion, returning the outputs.
+fn run(interpreter: Interpreter, program: &[Word], inputs: &[Word], patch: &Patch) -> Vec<Word> {
+    match interpreter {
+        Interpreter::Baseline => {
+            let mut computer = baseline::Computer::new(program, inputs);
+            if let (Some(noun), Some(verb)) = (patch.get(1), patch.get(2)) {
+                computer.patch((noun, verb));
+            }
+            computer.run().ok();
+            computer.outputs().to_vec()
+        }
+        Interpreter::Uncached | Interpreter::Cached => {
+            let mut computer = Computer::new(program, inputs);
+            computer.set_decode_cache(matches!(interpreter, Interpreter::Cached));
+            computer.patch(patch);
+            assert_eq!(computer.run(), Ok(Status::Halted));
+            computer.outputs().to_vec()
+        }
+    }
+}
+
+fn brute_force(interpreter: Interpreter, program: &[Word]) {
+    for noun in 0..100 {
+        for verb in 0..100 {
+            run(interpreter, program, &[], &Patch::noun_verb(noun, verb));
+        }
+    }
+}
+
+/// Times the three interpreters in turn for `rounds` rounds of `repetitions` runs each and
+/// keeps the fastest round of each, so that a noisy neighbour slows down all three alike.
+fn compare<F: Fn(Interpreter)>(name: &str, rounds: u32, repetitions: u32, bench: F) {
+    let mut best = [Duration::MAX; 3];
+    for _ in 0..rounds {
+        let interpreters = [
+            Interpreter::Baseline,
+            Interpreter::Uncached,
+            Interpreter::Cached,
+        ];
+        for (best, &interpreter) in best.iter_mut().zip(&interpreters) {
+            *best = (*best).min(time(repetitions, || bench(interpreter)));
+        }
+    }
+    let [baseline, uncached, cached] = best;
+    let speedup = |d: Duration| baseline.as_secs_f64() / d.as_secs_f64();
+    println!(
+        "{:<18} baseline {:>9.2?}  uncached {:>9.2?} ({:.2}x)  cached {:>9.2?} ({:.2}x)",
+        name,
+        baseline,
+        uncached,
+        speedup(uncached),
+        cached,
+        speedup(cached)
+    );
+}
+
+fn main() {
+    let rounds = env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(5);
+    let day02 = parse_intcode(include_str!("../../../day02/input/input.txt")).unwrap();
+    let day09 = parse_intcode(include_str!("../../../day09/input/input.txt")).unwrap();
+    assert_eq!(
+        run(Interpreter::Baseline, &day09, &[1], &Patch::new()),
+        run(Interpreter::Cached, &day09, &[1], &Patch::new())
+    );
+
+    compare("day02 part 1", rounds, 1000, |interpreter| {
+        run(interpreter, &day02, &[], &Patch::noun_verb(12, 2));
+    });
+    compare("day02 brute force", rounds, 1, |interpreter| {
+        brute_force(interpreter, &day02)
+    });
+    compare("day09 part 2", rounds, 1, |interpreter| {
+        run(interpreter, &day09, &[2], &Patch::new());
+    });
+}
@@ -0,0 +1,337 @@
+//! Control-flow graph and code/data classification of a program.
+//!
+//! Code is found by following fall-through and immediate `jt`/`jf` targets from address 0.
+//! Jumps through memory can't be followed statically and are flagged as indirect, but
+//! constants computed from two immediates by `add`/`mul` are treated as extra entry points,
+//! since that is how return addresses get pushed for calls.
+
+use crate::{decode_at, Item, Mode, Op, Operand, Word};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Class {
+    Code,
+    Data,
+}
+
+/// A straight run of instructions that is only entered at the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    /// Addresses of the instructions in the block, in order.
+    pub instructions: Vec<usize>,
+    /// Starts of the blocks control may continue at.
+    pub successors: Vec<usize>,
+    /// Ends in a jump whose target is only known at run time.
+    pub indirect: bool,
+}
+
+/// A write that may change code: either a position-mode write to a code word, or a
+/// relative-mode write, whose address depends on the relative base.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CodeWrite {
+    pub ip: usize,
+    pub target: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    instructions: BTreeMap<usize, (Op, Vec<Operand>)>,
+    entries: BTreeSet<usize>,
+    blocks: BTreeMap<usize, Block>,
+    words: Vec<Class>,
+}
+
+/// Addresses control may continue at after the instruction at `address`, and whether it
+/// also jumps somewhere unknown.
+fn successors(address: usize, op: Op, operands: &[Operand]) -> (Vec<usize>, bool) {
+    let next = address + operands.len() + 1;
+    match op {
+        Op::Halt => (vec![], false),
+        Op::JumpIfTrue | Op::JumpIfFalse => {
+            let condition = operands[0];
+            let always = condition.mode == Mode::Immediate
+                && (condition.value != 0) == (op == Op::JumpIfTrue);
+            let never = condition.mode == Mode::Immediate && !always;
+            let target = operands[1];
+
+            let mut successors = Vec::new();
+            if !always {
+                successors.push(next);
+            }
+            let known = target.mode == Mode::Immediate;
+            if !never && known && target.value >= 0 {
+                successors.push(target.value as usize);
+            }
+            (successors, !never && !known)
+        }
+        _ => (vec![next], false),
+    }
+}
+
+impl Analysis {
+    pub fn new(program: &[Word]) -> Self {
+        let mut instructions = BTreeMap::new();
+        let mut entries = BTreeSet::new();
+        entries.insert(0);
+        let mut pending = vec![0];
+
+        while let Some(address) = pending.pop() {
+            if instructions.contains_key(&address) {
+                continue;
+            }
+            let (op, operands) = match decode_at(program, address) {
+                Some(decoded) => decoded,
+                None => continue,
+            };
+            if let (Op::Add, [x, y, _]) | (Op::Multiply, [x, y, _]) = (op, &operands[..]) {
+                if x.mode == Mode::Immediate && y.mode == Mode::Immediate {
+                    let value = if op == Op::Add {
+                        x.value.checked_add(y.value)
+                    } else {
+                        x.value.checked_mul(y.value)
+                    };
+                    if let Some(value) = value.and_then(|v| usize::try_from(v).ok()) {
+                        if decode_at(program, value).is_some() {
+                            entries.insert(value);
+                            pending.push(value);
+                        }
+                    }
+                }
+            }
+            pending.extend(successors(address, op, &operands).0);
+            instructions.insert(address, (op, operands));
+        }
+
+        let mut words = vec![Class::Data; program.len()];
+        for (&address, (_, operands)) in &instructions {
+            for word in &mut words[address..=address + operands.len()] {
+                *word = Class::Code;
+            }
+        }
+
+        let mut analysis = Self {
+            instructions,
+            entries,
+            blocks: BTreeMap::new(),
+            words,
+        };
+        analysis.blocks = analysis.build_blocks();
+        analysis
+    }
+
+    fn build_blocks(&self) -> BTreeMap<usize, Block> {
+        let mut leaders = self.entries.clone();
+        for (&address, (op, operands)) in &self.instructions {
+            if let Op::JumpIfTrue | Op::JumpIfFalse | Op::Halt = op {
+                leaders.extend(successors(address, *op, operands).0);
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+        for &start in leaders.iter().filter(|a| self.instructions.contains_key(a)) {
+            let mut block = Block {
+                start,
+                instructions: Vec::new(),
+                successors: Vec::new(),
+                indirect: false,
+            };
+            let mut address = start;
+            while let Some((op, operands)) = self.instructions.get(&address) {
+                block.instructions.push(address);
+                let (next, indirect) = successors(address, *op, operands);
+                let ends = *op == Op::JumpIfTrue || *op == Op::JumpIfFalse || *op == Op::Halt;
+                if ends || next.iter().any(|a| leaders.contains(a)) {
+                    block.successors = next
+                        .into_iter()
+                        .filter(|a| self.instructions.contains_key(a))
+                        .collect();
+                    block.indirect = indirect;
+                    break;
+                }
+                address = next[0];
+            }
+            blocks.insert(start, block);
+        }
+        blocks
+    }
+
+    pub fn instructions(&self) -> &BTreeMap<usize, (Op, Vec<Operand>)> {
+        &self.instructions
+    }
+
+    /// Address 0 and the likely return addresses.
+    pub fn entries(&self) -> &BTreeSet<usize> {
+        &self.entries
+    }
+
+    pub fn blocks(&self) -> &BTreeMap<usize, Block> {
+        &self.blocks
+    }
+
+    /// The block containing the instruction at `address`.
+    pub fn block_at(&self, address: usize) -> Option<&Block> {
+        self.blocks
+            .range(..=address)
+            .rev()
+            .find(|(_, block)| block.instructions.contains(&address))
+            .map(|(_, block)| block)
+    }
+
+    pub fn word(&self, address: usize) -> Class {
+        self.words.get(address).cloned().unwrap_or(Class::Data)
+    }
+
+    pub fn words(&self) -> &[Class] {
+        &self.words
+    }
+
+    /// Inclusive `(start, end)` ranges of code words.
+    pub fn code_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for (address, &word) in self.words.iter().enumerate() {
+            match ranges.last_mut() {
+                Some(last) if word == Class::Code && last.1 + 1 == address => last.1 = address,
+                _ if word == Class::Code => ranges.push((address, address)),
+                _ => (),
+            }
+        }
+        ranges
+    }
+
+    /// Addresses of jumps whose target is only known at run time.
+    pub fn indirect_jumps(&self) -> Vec<usize> {
+        self.instructions
+            .iter()
+            .filter(|&(&address, (op, operands))| successors(address, *op, operands).1)
+            .map(|(&address, _)| address)
+            .collect()
+    }
+
+    pub fn code_writes(&self) -> Vec<CodeWrite> {
+        self.instructions
+            .iter()
+            .filter_map(|(&ip, (op, operands))| {
+                let target = operands[op.write_param()?];
+                match target.mode {
+                    Mode::Relative => Some(CodeWrite { ip, target: None }),
+                    _ if target.value >= 0 && self.word(target.value as usize) == Class::Code => {
+                        Some(CodeWrite {
+                            ip,
+                            target: Some(target.value as usize),
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The graph in GraphViz DOT format, one box per block.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph intcode {\n    node [shape=box, fontname=monospace];\n");
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            for address in &block.instructions {
+                let (op, operands) = &self.instructions[address];
+                let item = Item::Instruction {
+                    op: *op,
+                    operands: operands.clone(),
+                };
+                write!(label, "{}: {}\\l", address, item).unwrap();
+            }
+            writeln!(dot, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+            for successor in &block.successors {
+                writeln!(dot, "    b{} -> b{};", block.start, successor).unwrap();
+            }
+            if block.indirect {
+                writeln!(
+                    dot,
+                    "    b{} -> unknown [style=dashed];\n    unknown [shape=plaintext, label=\"?\"];",
+                    block.start
+                )
+                .unwrap();
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    const PROGRAM: &str = "
+                in [x]
+                jt [x], #skip
+                out #1
+        skip:   add #ret, #0, [sp]
+                jt #1, #fn
+        ret:    hlt
+        fn:     add #1, #2, [x]
+                mul [x], #2, rb+0
+                jt #1, [sp]
+        x:      .data 0
+        sp:     .data 0";
+
+    #[test]
+    fn test_blocks() {
+        let program = assemble(PROGRAM).unwrap();
+        let analysis = Analysis::new(&program);
+
+        assert_eq!(analysis.entries().iter().collect::<Vec<_>>(), [&0, &14]);
+        let blocks: Vec<_> = analysis
+            .blocks()
+            .values()
+            .map(|b| {
+                (
+                    b.start,
+                    b.instructions.clone(),
+                    b.successors.clone(),
+                    b.indirect,
+                )
+            })
+            .collect();
+        assert_eq!(
+            blocks,
+            [
+                (0, vec![0, 2], vec![5, 7], false),
+                (5, vec![5], vec![7], false),
+                (7, vec![7, 11], vec![15], false),
+                (14, vec![14], vec![], false),
+                (15, vec![15, 19, 23], vec![], true),
+            ]
+        );
+        assert_eq!(analysis.block_at(19).map(|b| b.start), Some(15));
+        assert_eq!(analysis.indirect_jumps(), [23]);
+    }
+
+    #[test]
+    fn test_words_and_code_writes() {
+        let mut program = assemble(PROGRAM).unwrap();
+        program.extend(&[1, 2, 3]);
+        let analysis = Analysis::new(&program);
+
+        assert_eq!(analysis.code_ranges(), [(0, 25)]);
+        assert_eq!(analysis.word(26), Class::Data);
+        assert_eq!(analysis.word(100), Class::Data);
+        assert_eq!(
+            analysis.code_writes(),
+            [CodeWrite {
+                ip: 19,
+                target: None
+            }]
+        );
+
+        let dot = analysis.to_dot();
+        assert!(dot.starts_with("digraph intcode {"));
+        assert!(dot.contains("b0 -> b5;"));
+        assert!(dot.contains("b15 -> unknown [style=dashed];"));
+        assert!(dot.contains("[label=\"14: hlt\\l\"]"));
+    }
+}
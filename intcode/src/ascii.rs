@@ -0,0 +1,142 @@
+//! Helpers for programs that talk in ASCII.
+
+use crate::{Computer, Memory, Observer, Status, Word};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+/// Outputs split into text and the values that aren't ASCII, such as a final answer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AsciiOutput {
+    pub text: String,
+    pub values: Vec<Word>,
+}
+
+impl AsciiOutput {
+    pub fn decode(outputs: &[Word]) -> Self {
+        let mut decoded = Self::default();
+        for &value in outputs {
+            match ascii(value) {
+                Some(c) => decoded.text.push(c),
+                None => decoded.values.push(value),
+            }
+        }
+        decoded
+    }
+}
+
+fn ascii(value: Word) -> Option<char> {
+    if (0..128).contains(&value) {
+        Some(value as u8 as char)
+    } else {
+        None
+    }
+}
+
+impl<M, O> Computer<M, VecDeque<Word>, O> {
+    /// Queues every character of `text` as its code point.
+    pub fn push_str(&mut self, text: &str) {
+        for c in text.chars() {
+            self.push_input(c as Word);
+        }
+    }
+
+    /// Queues `line` followed by a newline.
+    pub fn push_line(&mut self, line: &str) {
+        self.push_str(line);
+        self.push_input('\n' as Word);
+    }
+}
+
+impl<M, I> Computer<M, I, Vec<Word>> {
+    pub fn ascii_output(&self) -> AsciiOutput {
+        AsciiOutput::decode(self.outputs())
+    }
+}
+
+/// Runs `computer` interactively: text is written as it comes, other values on lines of their
+/// own, and whenever the computer needs input a line is read from `input`. Returns once the
+/// computer halts or `input` ends.
+pub fn terminal<M: Memory, R: BufRead, W: Write>(
+    computer: &mut Computer<M>,
+    input: R,
+    output: W,
+) -> io::Result<Status> {
+    terminal_with(computer, input, output, &mut ())
+}
+
+/// `terminal`, reporting every executed instruction to `observer`.
+pub fn terminal_with<M: Memory, R: BufRead, W: Write, T: Observer>(
+    computer: &mut Computer<M>,
+    mut input: R,
+    mut output: W,
+    observer: &mut T,
+) -> io::Result<Status> {
+    let mut seen = computer.outputs().len();
+    loop {
+        let status = computer.run_with(observer).map_err(io::Error::other)?;
+        for &value in &computer.outputs()[seen..] {
+            match ascii(value) {
+                Some(c) => write!(output, "{}", c)?,
+                None => writeln!(output, "{}", value)?,
+            }
+        }
+        seen = computer.outputs().len();
+        output.flush()?;
+
+        if status == Status::Halted {
+            return Ok(status);
+        }
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(status);
+        }
+        computer.push_line(line.trim_end_matches(&['\r', '\n'][..]));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    const ECHO: &str = "
+                out #62
+                out #32
+        loop:   in [c]
+                out [c]
+                eq [c], #10, [t]
+                jf [t], #loop
+                out #1000
+                hlt
+        c:      .data 0
+        t:      .data 0";
+
+    #[test]
+    fn test_ascii_output() {
+        let mut computer = Computer::new(&assemble(ECHO).unwrap(), &[]);
+        computer.push_line("héllo");
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(
+            computer.ascii_output(),
+            AsciiOutput {
+                text: "> hllo\n".to_string(),
+                values: vec![233, 1000],
+            }
+        );
+    }
+
+    #[test]
+    fn test_terminal() {
+        let mut computer = Computer::new(&assemble(ECHO).unwrap(), &[]);
+        let mut output = Vec::new();
+        let status = terminal(&mut computer, &b"hi\r\nignored\n"[..], &mut output).unwrap();
+        assert_eq!(status, Status::Halted);
+        assert_eq!(String::from_utf8(output).unwrap(), "> hi\n1000\n");
+
+        let mut computer = Computer::new(&assemble(ECHO).unwrap(), &[]);
+        let mut output = Vec::new();
+        let status = terminal(&mut computer, &b""[..], &mut output).unwrap();
+        assert_eq!(status, Status::NeedsInput);
+        assert_eq!(output, b"> ");
+    }
+}
@@ -0,0 +1,513 @@
+//! A small assembler for hand-written Intcode.
+//!
+//! ```text
+//! ; comments run from `;` to the end of the line
+//! start:  in [x]
+//!         mul [x], #10, [x]
+//!         out [x]
+//!         jt #1, #start
+//! x:      .data 0
+//! stack:  .zero 16
+//! ```
+//!
+//! Operands are `#value` (immediate), `[address]` (position) and `rb+n`/`rb-n` (relative).
+//! Values may be numbers, labels, or a label with a numeric offset such as `[x+1]`.
+
+use crate::{Instruction, Mode, Op, Word};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+/// The longest program `assemble` produces, so `.zero` can't ask for any amount of memory.
+const MAX_PROGRAM_LEN: usize = 1 << 24;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl AsmError {
+    fn new(line: usize, column: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            column,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+#[derive(Debug)]
+struct Expr {
+    label: Option<String>,
+    offset: Word,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Debug)]
+enum Body {
+    Instruction(Op, Vec<(Mode, Expr)>),
+    Data(Vec<Expr>),
+    Zero(usize),
+}
+
+impl Body {
+    fn len(&self) -> usize {
+        match self {
+            Self::Instruction(op, _) => op.arity() + 1,
+            Self::Data(values) => values.len(),
+            Self::Zero(n) => *n,
+        }
+    }
+}
+
+struct Statement {
+    labels: Vec<(String, usize)>,
+    body: Option<Body>,
+}
+
+struct Cursor<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    source: &'a str,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: usize, source: &'a str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+            line,
+            source,
+        }
+    }
+
+    const fn column(&self) -> usize {
+        self.pos + 1
+    }
+
+    fn error(&self, message: impl Into<String>) -> AsmError {
+        AsmError::new(self.line, self.column(), message)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), AsmError> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected `{}`", c)))
+        }
+    }
+
+    fn at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.peek().is_none()
+    }
+
+    fn word(&mut self) -> String {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn number(&mut self) -> Result<Word, AsmError> {
+        self.skip_whitespace();
+        let column = self.column();
+        let start = self.pos;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse()
+            .map_err(|_| AsmError::new(self.line, column, "expected a number"))
+    }
+
+    /// `offset` plus the number that follows, or minus it if `negative`.
+    fn offset(&mut self, offset: Word, negative: bool) -> Result<Word, AsmError> {
+        self.skip_whitespace();
+        let column = self.column();
+        let number = self.number()?;
+        let offset = if negative {
+            offset.checked_sub(number)
+        } else {
+            offset.checked_add(number)
+        };
+        offset.ok_or_else(|| AsmError::new(self.line, column, "offset out of range"))
+    }
+
+    fn expr(&mut self) -> Result<Expr, AsmError> {
+        self.skip_whitespace();
+        let column = self.column();
+        let mut expr = Expr {
+            label: None,
+            offset: 0,
+            line: self.line,
+            column,
+        };
+
+        match self.peek() {
+            Some(c) if c == '-' || c.is_ascii_digit() => expr.offset = self.number()?,
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                expr.label = Some(self.word());
+                loop {
+                    if self.eat('+') {
+                        expr.offset = self.offset(expr.offset, false)?;
+                    } else if self.eat('-') {
+                        expr.offset = self.offset(expr.offset, true)?;
+                    } else {
+                        break;
+                    }
+                }
+            }
+            _ => return Err(self.error("expected a number or label")),
+        }
+
+        Ok(expr)
+    }
+
+    fn operand(&mut self) -> Result<(Mode, Expr), AsmError> {
+        self.skip_whitespace();
+        let column = self.column();
+        if self.eat('#') {
+            let expr = self.expr()?;
+            return Ok((Mode::Immediate, Expr { column, ..expr }));
+        }
+        if self.eat('[') {
+            let expr = self.expr()?;
+            self.expect(']')?;
+            return Ok((Mode::Position, Expr { column, ..expr }));
+        }
+
+        if self.word() != "rb" {
+            return Err(AsmError::new(
+                self.line,
+                column,
+                "expected `#value`, `[address]` or `rb+offset`",
+            ));
+        }
+        let mut expr = Expr {
+            label: None,
+            offset: 0,
+            line: self.line,
+            column,
+        };
+        if self.eat('+') {
+            expr.offset = self.offset(0, false)?;
+        } else if self.eat('-') {
+            expr.offset = self.offset(0, true)?;
+        }
+        Ok((Mode::Relative, expr))
+    }
+
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, AsmError>,
+    ) -> Result<Vec<T>, AsmError> {
+        let mut items = Vec::new();
+        if self.at_end() {
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            if !self.eat(',') {
+                return Ok(items);
+            }
+        }
+    }
+
+    /// Parses one line into its labels and an optional body.
+    fn statement(&mut self) -> Result<Statement, AsmError> {
+        let mut labels = Vec::new();
+
+        loop {
+            if self.at_end() {
+                return Ok(Statement { labels, body: None });
+            }
+            let column = self.column();
+            let word = self.word();
+            if word.is_empty() {
+                return Err(self.error(format!("unexpected `{}`", self.peek().unwrap())));
+            }
+            if self.eat(':') {
+                if word == "rb" || word.starts_with('.') || word.starts_with(char::is_numeric) {
+                    return Err(AsmError::new(
+                        self.line,
+                        column,
+                        format!("invalid label `{}`", word),
+                    ));
+                }
+                labels.push((word, column));
+                continue;
+            }
+
+            let body = match word.as_str() {
+                ".data" => Body::Data(self.list(Self::expr)?),
+                ".zero" => {
+                    let count = self.number()?;
+                    if count < 0 {
+                        return Err(AsmError::new(self.line, column, "negative `.zero` count"));
+                    }
+                    match usize::try_from(count) {
+                        Ok(count) if count <= MAX_PROGRAM_LEN => Body::Zero(count),
+                        _ => {
+                            return Err(AsmError::new(
+                                self.line,
+                                column,
+                                format!("`.zero` count over {}", MAX_PROGRAM_LEN),
+                            ))
+                        }
+                    }
+                }
+                _ => {
+                    let op = Op::from_mnemonic(&word).ok_or_else(|| {
+                        AsmError::new(self.line, column, format!("unknown mnemonic `{}`", word))
+                    })?;
+                    let operands = self.list(Self::operand)?;
+                    if operands.len() != op.arity() {
+                        return Err(AsmError::new(
+                            self.line,
+                            column,
+                            format!(
+                                "`{}` takes {} operands, got {}",
+                                word,
+                                op.arity(),
+                                operands.len()
+                            ),
+                        ));
+                    }
+                    if let Some(i) = op.write_param() {
+                        if operands[i].0 == Mode::Immediate {
+                            let expr = &operands[i].1;
+                            return Err(AsmError::new(
+                                expr.line,
+                                expr.column,
+                                "cannot store through an immediate operand",
+                            ));
+                        }
+                    }
+                    Body::Instruction(op, operands)
+                }
+            };
+
+            if !self.at_end() {
+                return Err(self.error(format!(
+                    "unexpected `{}`",
+                    self.source.chars().skip(self.pos).collect::<String>()
+                )));
+            }
+            return Ok(Statement {
+                labels,
+                body: Some(body),
+            });
+        }
+    }
+}
+
+fn resolve(expr: &Expr, labels: &HashMap<String, Word>) -> Result<Word, AsmError> {
+    let error = |message| AsmError::new(expr.line, expr.column, message);
+    match &expr.label {
+        Some(label) => labels
+            .get(label)
+            .ok_or_else(|| error(format!("undefined label `{}`", label)))?
+            .checked_add(expr.offset)
+            .ok_or_else(|| error(format!("`{}` plus {} is out of range", label, expr.offset))),
+        None => Ok(expr.offset),
+    }
+}
+
+pub fn assemble(source: &str) -> Result<Vec<Word>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut bodies = Vec::new();
+    let mut address = 0;
+
+    for (i, text) in source.lines().enumerate() {
+        let text = text.split(';').next().unwrap_or("");
+        let statement = Cursor::new(i + 1, text).statement()?;
+        for (label, column) in statement.labels {
+            if labels.insert(label.clone(), address as Word).is_some() {
+                return Err(AsmError::new(
+                    i + 1,
+                    column,
+                    format!("duplicate label `{}`", label),
+                ));
+            }
+        }
+        if let Some(body) = statement.body {
+            address += body.len();
+            if address > MAX_PROGRAM_LEN {
+                return Err(AsmError::new(
+                    i + 1,
+                    1,
+                    format!("program longer than {} words", MAX_PROGRAM_LEN),
+                ));
+            }
+            bodies.push(body);
+        }
+    }
+
+    let mut program = Vec::with_capacity(address);
+    for body in bodies {
+        match body {
+            Body::Instruction(op, operands) => {
+                let mut modes = [Mode::Position; 3];
+                for (mode, operand) in modes.iter_mut().zip(operands.iter()) {
+                    *mode = operand.0;
+                }
+                program.push(Instruction { op, modes }.encode());
+                for (_, expr) in operands {
+                    program.push(resolve(&expr, &labels)?);
+                }
+            }
+            Body::Data(values) => {
+                for expr in values {
+                    program.push(resolve(&expr, &labels)?);
+                }
+            }
+            Body::Zero(n) => program.resize(program.len() + n, 0),
+        }
+    }
+
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{disassemble, load_intcode, run_intcode};
+
+    #[test]
+    fn test_assemble() {
+        let program = assemble(
+            "
+            ; multiply the input by ten until it is zero
+            start:  in [x]          ; read
+                    mul [x], #10, [x]
+                    out [x]
+                    jt [x], #start
+            end:    hlt
+            x:      .data 0
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            program,
+            vec![3, 12, 1002, 12, 10, 12, 4, 12, 1005, 12, 0, 99, 0]
+        );
+        assert_eq!(run_intcode(&program, &[7, 0]).outputs(), &[70, 0]);
+    }
+
+    #[test]
+    fn test_relative_and_directives() {
+        let program = assemble(
+            "arb #buf
+             in rb+1
+             out rb+1
+             out [buf-1]
+             hlt
+             .data 7, buf
+             buf: .zero 2",
+        )
+        .unwrap();
+        assert_eq!(
+            program,
+            vec![109, 11, 203, 1, 204, 1, 4, 10, 99, 7, 11, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_disassembly_round_trip() {
+        let program = load_intcode("../day09/input/input.txt");
+        let source = disassemble(&program)
+            .iter()
+            .map(|line| format!("{}\n", line.item))
+            .collect::<String>();
+        assert_eq!(assemble(&source), Ok(program));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source: &str| assemble(source).unwrap_err();
+        assert_eq!(
+            error("add #1, #2, #3"),
+            AsmError::new(1, 13, "cannot store through an immediate operand")
+        );
+        assert_eq!(error("  nop").column, 3);
+        assert_eq!(error("out [x]").message, "undefined label `x`");
+        assert_eq!(
+            error("a: hlt\n a: hlt"),
+            AsmError::new(2, 2, "duplicate label `a`")
+        );
+        assert_eq!(error("out #1, #2").message, "`out` takes 1 operands, got 2");
+        assert_eq!(error("out #1 2"), AsmError::new(1, 8, "unexpected `2`"));
+        assert_eq!(error("out rx").column, 5);
+
+        assert_eq!(
+            error(&format!("out [x+{}]\nx: hlt", Word::MAX)),
+            AsmError::new(1, 5, format!("`x` plus {} is out of range", Word::MAX))
+        );
+        assert_eq!(
+            error(&format!("out [x-1+{}]\nx: hlt", Word::MAX)).message,
+            format!("`x` plus {} is out of range", Word::MAX - 1)
+        );
+        assert_eq!(
+            error(&format!("out [x-2-{}]", Word::MAX)),
+            AsmError::new(1, 10, "offset out of range")
+        );
+        assert_eq!(
+            error(&format!("out rb-{}", Word::MIN)),
+            AsmError::new(1, 8, "offset out of range")
+        );
+        assert_eq!(
+            error(&format!(".zero {}", Word::MAX)),
+            AsmError::new(1, 1, format!("`.zero` count over {}", MAX_PROGRAM_LEN))
+        );
+        assert_eq!(
+            error(&format!(".zero {}\nhlt", MAX_PROGRAM_LEN)),
+            AsmError::new(
+                2,
+                1,
+                format!("program longer than {} words", MAX_PROGRAM_LEN)
+            )
+        );
+    }
+}
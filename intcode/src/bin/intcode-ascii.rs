@@ -0,0 +1,28 @@
+use intcode::{load_intcode, terminal_with, Computer, Replay};
+use std::env;
+use std::io;
+
+/// Runs a program on the terminal. `--record <path>` writes a replay of the session.
+fn main() -> io::Result<()> {
+    let mut path = "input/input.txt".to_string();
+    let mut record = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record = args.next(),
+            _ => path = arg,
+        }
+    }
+
+    let program = load_intcode(&path);
+    let mut computer = Computer::new(&program, &[]);
+    let mut replay = Replay::new(&program);
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    terminal_with(&mut computer, stdin.lock(), stdout.lock(), &mut replay)?;
+    if let Some(record) = record {
+        replay.save(&record)?;
+    }
+    Ok(())
+}
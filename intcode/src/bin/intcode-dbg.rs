@@ -0,0 +1,419 @@
+use intcode::{load_intcode, Computer, Item, Limits, Op, RingTracer, Snapshot, Status, Word};
+use std::collections::BTreeSet;
+use std::env;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+s [n]          step n instructions (default 1)
+c              continue until a breakpoint, watchpoint, halt or missing input
+sb [n]         step back n instructions (default 1)
+rc <addr>      run backwards to the last time <addr> was about to execute
+who <addr>     show the last instruction that wrote to a memory cell
+b <addr>       toggle breakpoint on address
+bo <op>        toggle breakpoint on opcode (number or mnemonic)
+w <addr>       toggle watchpoint on memory cell
+info           list breakpoints and watchpoints
+r              show ip, base and pending inputs
+x <addr> [n]   dump n memory cells (default 8, at most 4096)
+l [addr] [n]   disassemble n instructions (default from ip, 10)
+i <v>...       queue inputs
+o              show outputs
+t              show the last 32 executed instructions
+save <path>    write a snapshot of the computer
+load <path>    replace the computer with a saved snapshot
+q              quit";
+
+/// The debugger can go back `RECORD_INTERVAL * RECORD_CHECKPOINTS` instructions.
+const RECORD_INTERVAL: u64 = 10_000;
+const RECORD_CHECKPOINTS: usize = 16;
+/// Memory cells a loaded snapshot may allocate.
+const LOAD_MEMORY_LIMIT: usize = 1 << 26;
+/// Most cells `x` dumps at once.
+const MAX_DUMP: Word = 4096;
+
+/// Why `Debugger::resume` stopped executing.
+#[derive(Debug, PartialEq)]
+enum Stop {
+    Steps,
+    Breakpoint(Word),
+    Opcode(Word, Word),
+    Watch { address: Word, old: Word, new: Word },
+    NeedsInput,
+    Halted,
+    Error(String),
+}
+
+struct Debugger {
+    computer: Computer,
+    breakpoints: BTreeSet<Word>,
+    opcodes: BTreeSet<Word>,
+    watches: BTreeSet<Word>,
+    history: RingTracer,
+}
+
+impl Debugger {
+    fn new(mut computer: Computer) -> Self {
+        computer.record(RECORD_INTERVAL, RECORD_CHECKPOINTS);
+        Self {
+            computer,
+            breakpoints: BTreeSet::new(),
+            opcodes: BTreeSet::new(),
+            watches: BTreeSet::new(),
+            history: RingTracer::new(32),
+        }
+    }
+
+    fn cell(&self, address: usize) -> Word {
+        self.computer.peek(address)
+    }
+
+    /// Executes up to `steps` instructions, or without limit if `None`. The instruction at the
+    /// current ip always runs, so resuming from a breakpoint doesn't stop on it again.
+    fn resume(&mut self, steps: Option<usize>, out: &mut impl Write) -> io::Result<Stop> {
+        let mut executed = 0;
+
+        loop {
+            if steps.is_some_and(|n| executed >= n) {
+                return Ok(Stop::Steps);
+            }
+
+            let ip = self.computer.ip();
+            if executed > 0 {
+                if self.breakpoints.contains(&ip) {
+                    return Ok(Stop::Breakpoint(ip));
+                }
+                let opcode = self.cell(ip as usize) % 100;
+                if self.opcodes.contains(&opcode) {
+                    return Ok(Stop::Opcode(opcode, ip));
+                }
+            }
+
+            match self.computer.step_instruction_with(&mut self.history) {
+                Ok(Some(Status::Output(value))) => writeln!(out, "output: {}", value)?,
+                Ok(Some(Status::NeedsInput)) => return Ok(Stop::NeedsInput),
+                Ok(Some(Status::Halted)) => return Ok(Stop::Halted),
+                Ok(None) => (),
+                Err(e) => return Ok(Stop::Error(e.to_string())),
+            }
+            executed += 1;
+
+            if let Some(write) = self.history.last().and_then(|e| e.write) {
+                if self.watches.contains(&write.address) && write.old != write.value {
+                    return Ok(Stop::Watch {
+                        address: write.address,
+                        old: write.old,
+                        new: write.value,
+                    });
+                }
+            }
+        }
+    }
+
+    fn report(&self, stop: &Stop, out: &mut impl Write) -> io::Result<()> {
+        match stop {
+            Stop::Steps => (),
+            Stop::Breakpoint(ip) => writeln!(out, "breakpoint at {}", ip)?,
+            Stop::Opcode(opcode, ip) => writeln!(out, "opcode {} at {}", opcode, ip)?,
+            Stop::Watch { address, old, new } => {
+                writeln!(out, "watch [{}]: {} -> {}", address, old, new)?
+            }
+            Stop::NeedsInput => writeln!(out, "waiting for input")?,
+            Stop::Halted => writeln!(out, "halted")?,
+            Stop::Error(e) => writeln!(out, "error: {}", e)?,
+        }
+        self.list(self.computer.ip(), 1, out)
+    }
+
+    fn list(&self, address: Word, count: usize, out: &mut impl Write) -> io::Result<()> {
+        let memory = self.computer.memory();
+        let mut address = address.max(0) as usize;
+
+        for _ in 0..count {
+            if address >= memory.len() {
+                break;
+            }
+            let marker = if address as Word == self.computer.ip() {
+                "=>"
+            } else {
+                "  "
+            };
+            let item = Item::at(memory, address);
+            writeln!(out, "{} {:>5}  {}", marker, address, item)?;
+            address += item.size();
+        }
+
+        Ok(())
+    }
+
+    /// Runs one command line. Returns `false` when the user asks to quit.
+    fn command(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(true),
+        };
+        let args: Vec<&str> = words.collect();
+        let number = |i: usize| args.get(i).and_then(|arg| arg.parse::<Word>().ok());
+
+        match command {
+            "s" => {
+                let stop = self.resume(Some(number(0).unwrap_or(1).max(0) as usize), out)?;
+                self.report(&stop, out)?;
+            }
+            "c" => {
+                let stop = self.resume(None, out)?;
+                self.report(&stop, out)?;
+            }
+            "sb" => {
+                let steps = number(0).unwrap_or(1).max(0);
+                let stepped = (0..steps).take_while(|_| self.computer.step_back()).count();
+                if stepped < steps as usize {
+                    writeln!(out, "start of recording after {} steps back", stepped)?;
+                }
+                self.report(&Stop::Steps, out)?;
+            }
+            "rc" => match number(0) {
+                Some(address) => {
+                    if !self.computer.run_back_to(address) {
+                        writeln!(out, "{} not reached in the recording", address)?;
+                    }
+                    self.report(&Stop::Steps, out)?;
+                }
+                None => writeln!(out, "usage: rc <addr>")?,
+            },
+            "who" => match number(0) {
+                Some(address) => match self.computer.last_write(address) {
+                    Some((step, execution)) => writeln!(out, "step {}: {}", step, execution)?,
+                    None => writeln!(out, "no write to [{}] in the recording", address)?,
+                },
+                None => writeln!(out, "usage: who <addr>")?,
+            },
+            "b" => match number(0) {
+                Some(address) if self.breakpoints.remove(&address) => {
+                    writeln!(out, "removed breakpoint at {}", address)?
+                }
+                Some(address) => {
+                    self.breakpoints.insert(address);
+                    writeln!(out, "breakpoint at {}", address)?;
+                }
+                None => writeln!(out, "usage: b <addr>")?,
+            },
+            "bo" => {
+                let opcode = number(0).or_else(|| {
+                    args.first()
+                        .and_then(|arg| Op::from_mnemonic(arg))
+                        .map(Op::code)
+                });
+                match opcode {
+                    Some(opcode) if self.opcodes.remove(&opcode) => {
+                        writeln!(out, "removed opcode breakpoint {}", opcode)?
+                    }
+                    Some(opcode) => {
+                        self.opcodes.insert(opcode);
+                        writeln!(out, "breakpoint on opcode {}", opcode)?;
+                    }
+                    None => writeln!(out, "usage: bo <op>")?,
+                }
+            }
+            "w" => match number(0) {
+                Some(address) if self.watches.remove(&address) => {
+                    writeln!(out, "removed watchpoint on [{}]", address)?
+                }
+                Some(address) if address >= 0 => {
+                    self.watches.insert(address);
+                    writeln!(
+                        out,
+                        "watching [{}] = {}",
+                        address,
+                        self.cell(address as usize)
+                    )?;
+                }
+                _ => writeln!(out, "usage: w <addr>")?,
+            },
+            "info" => {
+                writeln!(out, "breakpoints: {:?}", self.breakpoints)?;
+                writeln!(out, "opcodes: {:?}", self.opcodes)?;
+                writeln!(out, "watchpoints: {:?}", self.watches)?;
+            }
+            "r" => writeln!(
+                out,
+                "ip={} base={} halted={} inputs={:?}",
+                self.computer.ip(),
+                self.computer.base(),
+                self.computer.is_halted(),
+                self.computer.inputs()
+            )?,
+            "x" => match number(0) {
+                Some(address) if address >= 0 => {
+                    let count = number(1).unwrap_or(8).clamp(0, MAX_DUMP);
+                    for address in address..address.saturating_add(count) {
+                        writeln!(out, "{:>5}: {}", address, self.cell(address as usize))?;
+                    }
+                }
+                _ => writeln!(out, "usage: x <addr> [n]")?,
+            },
+            "l" => {
+                let address = number(0).unwrap_or_else(|| self.computer.ip());
+                self.list(address, number(1).unwrap_or(10).max(0) as usize, out)?;
+            }
+            "i" => {
+                for arg in args {
+                    match arg.parse() {
+                        Ok(value) => self.computer.push_input(value),
+                        Err(_) => writeln!(out, "not a number: {}", arg)?,
+                    }
+                }
+            }
+            "o" => writeln!(out, "{:?}", self.computer.outputs())?,
+            "t" => write!(out, "{}", self.history.dump())?,
+            "save" | "load" if args.len() != 1 => writeln!(out, "usage: {} <path>", command)?,
+            "save" => match self.computer.snapshot().save(args[0]) {
+                Ok(()) => writeln!(out, "saved {}", args[0])?,
+                Err(e) => writeln!(out, "error: {}", e)?,
+            },
+            "load" => match Snapshot::load(args[0]) {
+                Ok(snapshot) => {
+                    let limits = Limits::none().memory(LOAD_MEMORY_LIMIT);
+                    match Computer::try_restore(&snapshot, limits) {
+                        Ok(computer) => {
+                            self.computer = computer;
+                            self.computer.set_limits(Limits::none());
+                            self.computer.record(RECORD_INTERVAL, RECORD_CHECKPOINTS);
+                            self.report(&Stop::Steps, out)?;
+                        }
+                        Err(e) => writeln!(out, "error: {}", e)?,
+                    }
+                }
+                Err(e) => writeln!(out, "error: {}", e)?,
+            },
+            "h" | "help" => writeln!(out, "{}", HELP)?,
+            "q" => return Ok(false),
+            _ => writeln!(out, "unknown command `{}` (try `h`)", command)?,
+        }
+
+        Ok(true)
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let path = args.next().unwrap_or_else(|| "input/input.txt".to_string());
+    let inputs: Vec<Word> = args.filter_map(|arg| arg.parse().ok()).collect();
+    let mut debugger = Debugger::new(Computer::new(&load_intcode(&path), &inputs));
+
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    debugger.list(0, 1, &mut out)?;
+
+    loop {
+        write!(out, "(dbg) ")?;
+        out.flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 || !debugger.command(&line, &mut out)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::assemble;
+
+    fn debugger(source: &str, inputs: &[Word]) -> Debugger {
+        Debugger::new(Computer::new(&assemble(source).unwrap(), inputs))
+    }
+
+    const COUNTDOWN: &str = "
+        loop:   out [n]
+                add [n], #-1, [n]
+                jt [n], #loop
+                hlt
+        n:      .data 3";
+
+    #[test]
+    fn test_breakpoints() {
+        let mut out = Vec::new();
+        let mut dbg = debugger(COUNTDOWN, &[]);
+
+        assert_eq!(dbg.resume(Some(2), &mut out).unwrap(), Stop::Steps);
+        assert_eq!(dbg.computer.ip(), 6);
+
+        dbg.command("b 6", &mut out).unwrap();
+        assert_eq!(dbg.resume(None, &mut out).unwrap(), Stop::Breakpoint(6));
+        assert_eq!(dbg.computer.outputs(), &[3, 2]);
+
+        dbg.command("b 6", &mut out).unwrap();
+        dbg.command("bo hlt", &mut out).unwrap();
+        assert_eq!(dbg.resume(None, &mut out).unwrap(), Stop::Opcode(99, 9));
+        assert_eq!(dbg.resume(None, &mut out).unwrap(), Stop::Halted);
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut out = Vec::new();
+        let mut dbg = debugger(COUNTDOWN, &[]);
+
+        dbg.command("w 10", &mut out).unwrap();
+        assert_eq!(
+            dbg.resume(None, &mut out).unwrap(),
+            Stop::Watch {
+                address: 10,
+                old: 3,
+                new: 2
+            }
+        );
+        assert_eq!(dbg.computer.ip(), 6);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut out = Vec::new();
+        let mut dbg = debugger(COUNTDOWN, &[]);
+
+        assert_eq!(dbg.resume(None, &mut out).unwrap(), Stop::Halted);
+        dbg.command("sb 2", &mut out).unwrap();
+        assert_eq!(dbg.computer.ip(), 6);
+        dbg.command("rc 0", &mut out).unwrap();
+        assert_eq!(dbg.computer.outputs(), &[3, 2]);
+
+        out.clear();
+        dbg.command("who 10", &mut out).unwrap();
+        assert!(String::from_utf8(out)
+            .unwrap()
+            .starts_with("step 4:     2  add 2, -1 -> [10] = 1"));
+    }
+
+    #[test]
+    fn test_dump() {
+        let mut out = Vec::new();
+        let mut dbg = debugger(COUNTDOWN, &[]);
+
+        dbg.command("x 9 2", &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "    9: 99\n   10: 3\n");
+
+        let mut out = Vec::new();
+        dbg.command(&format!("x {} {}", Word::MAX - 1, Word::MAX), &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 1);
+
+        let mut out = Vec::new();
+        dbg.command(&format!("x 0 {}", Word::MAX), &mut out)
+            .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), 4096);
+    }
+
+    #[test]
+    fn test_inputs() {
+        let mut out = Vec::new();
+        let mut dbg = debugger("in [0]\nout [0]\nhlt", &[]);
+
+        assert_eq!(dbg.resume(None, &mut out).unwrap(), Stop::NeedsInput);
+        dbg.command("i 42", &mut out).unwrap();
+        assert_eq!(dbg.resume(None, &mut out).unwrap(), Stop::Halted);
+        assert_eq!(String::from_utf8(out).unwrap(), "output: 42\n");
+    }
+}
@@ -0,0 +1,21 @@
+use intcode::{listing, load_intcode, Analysis};
+use std::env;
+
+/// Prints a listing, or the control-flow graph in DOT format with `--dot`.
+fn main() {
+    let mut dot = false;
+    let mut path = "input/input.txt".to_string();
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--dot" => dot = true,
+            _ => path = arg,
+        }
+    }
+
+    let program = load_intcode(&path);
+    if dot {
+        print!("{}", Analysis::new(&program).to_dot());
+    } else {
+        print!("{}", listing(&program));
+    }
+}
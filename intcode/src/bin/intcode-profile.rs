@@ -0,0 +1,43 @@
+use intcode::{load_intcode, Computer, Patch, Profiler};
+use std::env;
+use std::process;
+
+/// Runs a program with the given inputs and prints a profile, as JSON with `--json`.
+/// `--patch 1=12,2=2` writes to memory before running.
+fn main() {
+    let mut json = false;
+    let mut patch = Patch::new();
+    let mut path = "input/input.txt".to_string();
+    let mut inputs = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--patch" => {
+                let spec = args.next().unwrap_or_default();
+                patch = spec.parse().unwrap_or_else(|e| {
+                    eprintln!("{}", e);
+                    process::exit(2);
+                });
+            }
+            _ => match arg.parse() {
+                Ok(input) => inputs.push(input),
+                Err(_) => path = arg,
+            },
+        }
+    }
+
+    let mut computer = Computer::new(&load_intcode(&path), &inputs);
+    computer.patch(&patch);
+    let mut profiler = Profiler::new();
+    let result = computer.run_profiled(&mut profiler);
+    if json {
+        println!("{}", profiler.to_json());
+    } else {
+        println!("{:?}, outputs: {:?}\n", result, computer.outputs());
+        print!("{}", profiler.report(20));
+    }
+    if result.is_err() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,32 @@
+use intcode::{load_intcode, Replay};
+use std::env;
+use std::process;
+
+/// Instructions to run before giving up on a replay, unless given on the command line.
+const MAX_STEPS: u64 = 1_000_000_000;
+
+/// Re-runs a program with the inputs of a recorded session and reports the first divergence.
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let max_steps = match args.len() {
+        2 => Some(MAX_STEPS),
+        3 => args[2].parse().ok(),
+        _ => None,
+    };
+    let max_steps = max_steps.unwrap_or_else(|| {
+        eprintln!("usage: intcode-replay <program> <replay> [max-steps]");
+        process::exit(2);
+    });
+
+    let replay = Replay::load(&args[1]).unwrap_or_else(|e| {
+        eprintln!("{}: {}", args[1], e);
+        process::exit(2);
+    });
+    match replay.check(&load_intcode(&args[0]), max_steps) {
+        Ok(()) => println!("ok: {} events replayed", replay.events.len()),
+        Err(divergence) => {
+            println!("diverged: {}", divergence);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crate::Instruction;
+
+/// Only addresses below this are cached, so a program jumping far out into sparse memory
+/// doesn't make the cache allocate everything below it.
+const CACHED_ADDRESSES: usize = 1 << 16;
+
+/// Decoded instructions keyed by the address of their opcode word. Operands are still read
+/// from memory on every execution, so only a write to the opcode word itself invalidates an
+/// entry.
+#[derive(Debug, Clone)]
+pub(crate) struct DecodeCache {
+    enabled: bool,
+    entries: Vec<Option<Instruction>>,
+}
+
+impl Default for DecodeCache {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl DecodeCache {
+    pub(crate) const fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.entries.clear();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, address: usize) -> Option<Instruction> {
+        self.entries.get(address).cloned().flatten()
+    }
+
+    #[inline]
+    pub(crate) fn insert(&mut self, address: usize, instruction: Instruction) {
+        if !self.enabled || address >= CACHED_ADDRESSES {
+            return;
+        }
+        if self.entries.len() <= address {
+            let len = (address + 1)
+                .next_power_of_two()
+                .clamp(64, CACHED_ADDRESSES);
+            self.entries.resize(len, None);
+        }
+        self.entries[address] = Some(instruction);
+    }
+
+    #[inline]
+    pub(crate) fn invalidate(&mut self, address: usize) {
+        if let Some(entry) = self.entries.get_mut(address) {
+            *entry = None;
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use crate::{Instruction, Mode, Op, Word};
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    pub mode: Mode,
+    pub value: Word,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            Mode::Position => write!(f, "[{}]", self.value),
+            Mode::Immediate => write!(f, "#{}", self.value),
+            Mode::Relative if self.value < 0 => write!(f, "rb-{}", self.value.unsigned_abs()),
+            Mode::Relative => write!(f, "rb+{}", self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Instruction { op: Op, operands: Vec<Operand> },
+    Data(Word),
+}
+
+impl Item {
+    /// Decodes the word at `address`, falling back to `.data` if it isn't an instruction.
+    pub fn at(program: &[Word], address: usize) -> Self {
+        match decode_at(program, address) {
+            Some((op, operands)) => Self::Instruction { op, operands },
+            None => Self::Data(program[address]),
+        }
+    }
+
+    /// Number of words the item covers.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Instruction { operands, .. } => operands.len() + 1,
+            Self::Data(_) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Instruction { op, operands } => {
+                write!(f, "{}", op.mnemonic())?;
+                for (i, operand) in operands.iter().enumerate() {
+                    write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+                }
+                Ok(())
+            }
+            Self::Data(value) => write!(f, ".data {}", value),
+        }
+    }
+}
+
+/// One row of a listing: the decoded item together with the raw words it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub address: usize,
+    pub words: Vec<Word>,
+    pub item: Item,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let words = self
+            .words
+            .iter()
+            .map(|w| w.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(f, "{:>5}  {:<24} {}", self.address, words, self.item)
+    }
+}
+
+/// Decodes the instruction starting at `address`, or `None` if the words there can't be an
+/// instruction the computer would accept (bad opcode or mode, a write through an immediate
+/// operand, or parameters running off the end of the program).
+pub fn decode_at(program: &[Word], address: usize) -> Option<(Op, Vec<Operand>)> {
+    let instr = Instruction::try_from(*program.get(address)?).ok()?;
+    let arity = instr.op.arity();
+    let params = program.get(address + 1..address + 1 + arity)?;
+
+    if let Some(i) = instr.op.write_param() {
+        if instr.modes[i] == Mode::Immediate {
+            return None;
+        }
+    }
+
+    let operands = params
+        .iter()
+        .zip(instr.modes.iter())
+        .map(|(&value, &mode)| Operand { mode, value })
+        .collect();
+    Some((instr.op, operands))
+}
+
+/// Linear sweep over the whole program. Data that happens to look like an instruction is
+/// listed as one, so the result needs a human eye where code and data are interleaved.
+pub fn disassemble(program: &[Word]) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut address = 0;
+
+    while address < program.len() {
+        let item = Item::at(program, address);
+        let len = item.size();
+        lines.push(Line {
+            address,
+            words: program[address..address + len].to_vec(),
+            item,
+        });
+        address += len;
+    }
+
+    lines
+}
+
+pub fn listing(program: &[Word]) -> String {
+    disassemble(program)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listing() {
+        let program = [1002, 4, 3, 4, 33, 109, -3, 204, 5, 1105, 1, 0, 99, 42, 1101];
+        let expected = "    0  1002 4 3 4               mul [4], #3, [4]
+    4  33                       .data 33
+    5  109 -3                   arb #-3
+    7  204 5                    out rb+5
+    9  1105 1 0                 jt #1, #0
+   12  99                       hlt
+   13  42                       .data 42
+   14  1101                     .data 1101
+";
+        assert_eq!(listing(&program), expected);
+    }
+
+    #[test]
+    fn test_most_negative_offset() {
+        let lines = disassemble(&[209, Word::MIN]);
+        assert_eq!(
+            lines[0].item.to_string(),
+            format!("arb rb-{}", Word::MIN.unsigned_abs())
+        );
+    }
+
+    #[test]
+    fn test_immediate_write_is_data() {
+        let lines = disassemble(&[11101, 1, 1, 0]);
+        assert_eq!(lines[0].item, Item::Data(11101));
+        assert_eq!(lines.len(), 4);
+    }
+}
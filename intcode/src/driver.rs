@@ -0,0 +1,155 @@
+//! Async driver for `Computer`, enabled by the `async` feature.
+//!
+//! Reads await the next item of a `Stream` and outputs are fed to a `Sink`. Nothing here
+//! depends on a runtime; the `tokio` feature adds `serve` on top for line based sockets.
+
+use crate::{Computer, IntcodeError, Memory, Status, Word};
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsyncError<E> {
+    Intcode(IntcodeError),
+    Sink(E),
+}
+
+impl<E: fmt::Display> fmt::Display for AsyncError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Intcode(e) => write!(f, "{}", e),
+            Self::Sink(e) => write!(f, "output sink failed: {}", e),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for AsyncError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Intcode(e) => Some(e),
+            Self::Sink(e) => Some(e),
+        }
+    }
+}
+
+impl<M: Memory> Computer<M> {
+    /// Runs until the computer halts, or needs input after `inputs` has ended. Queued inputs
+    /// are used before awaiting the stream, and the sink is flushed before every wait so a
+    /// peer never waits on outputs stuck in a buffer.
+    ///
+    /// Outputs are taken back out of `outputs()` once they are fed to the sink, so a long
+    /// session doesn't hold on to all of them.
+    pub async fn run_async<S, K>(
+        &mut self,
+        inputs: &mut S,
+        outputs: &mut K,
+    ) -> Result<Status, AsyncError<K::Error>>
+    where
+        S: Stream<Item = Word> + Unpin,
+        K: Sink<Word> + Unpin,
+    {
+        let status = loop {
+            match self.step().map_err(AsyncError::Intcode)? {
+                Status::Output(value) => {
+                    outputs.feed(value).await.map_err(AsyncError::Sink)?;
+                    self.outputs.pop();
+                }
+                Status::NeedsInput => {
+                    outputs.flush().await.map_err(AsyncError::Sink)?;
+                    match inputs.next().await {
+                        Some(value) => self.push_input(value),
+                        None => break Status::NeedsInput,
+                    }
+                }
+                Status::Halted => break Status::Halted,
+            }
+        };
+        outputs.flush().await.map_err(AsyncError::Sink)?;
+        Ok(status)
+    }
+}
+
+/// Runs `computer` against a line based connection: every line read is one input, every
+/// output is written as its own line. Lines that aren't integers end the input.
+#[cfg(feature = "tokio")]
+pub async fn serve<M, R, W>(
+    computer: &mut Computer<M>,
+    reader: R,
+    writer: W,
+) -> Result<Status, AsyncError<std::io::Error>>
+where
+    M: Memory,
+    R: tokio::io::AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use futures::{sink, stream};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    let lines = BufReader::new(reader).lines();
+    let mut inputs = Box::pin(stream::unfold(lines, |mut lines| async move {
+        let line = lines.next_line().await.ok()??;
+        let value = line.trim().parse().ok()?;
+        Some((value, lines))
+    }));
+    let mut outputs = Box::pin(sink::unfold(writer, |mut writer, value: Word| async move {
+        writer.write_all(format!("{}\n", value).as_bytes()).await?;
+        writer.flush().await?;
+        Ok::<_, std::io::Error>(writer)
+    }));
+    computer.run_async(&mut inputs, &mut outputs).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+    use crate::fixtures::DOUBLER;
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+    use futures::stream;
+
+    #[test]
+    fn test_stream_and_sink() {
+        let mut computer = Computer::new(&assemble(DOUBLER).unwrap(), &[1]);
+        let mut inputs = stream::iter(vec![2, 3, 0, 4]);
+        let (mut tx, rx) = mpsc::unbounded();
+
+        let status = block_on(computer.run_async(&mut inputs, &mut tx));
+        assert_eq!(status, Ok(Status::Halted));
+        drop(tx);
+        assert_eq!(block_on(rx.collect::<Vec<_>>()), vec![2, 4, 6]);
+        assert_eq!(block_on(inputs.next()), Some(4));
+
+        let mut computer = Computer::new(&assemble(DOUBLER).unwrap(), &[]);
+        let (mut tx, _rx) = mpsc::unbounded();
+        let status = block_on(computer.run_async(&mut stream::iter(vec![5]), &mut tx));
+        assert_eq!(status, Ok(Status::NeedsInput));
+        assert_eq!(computer.outputs(), &[]);
+        assert_eq!(computer.counters().outputs, 1);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_loopback() {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        use tokio::net::{TcpListener, TcpStream};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (reader, writer) = socket.into_split();
+            let mut computer = Computer::new(&assemble(DOUBLER).unwrap(), &[]);
+            serve(&mut computer, reader, writer).await
+        });
+
+        let (reader, mut writer) = TcpStream::connect(address).await.unwrap().into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"21\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("42"));
+        writer.write_all(b"5\n0\n").await.unwrap();
+        assert_eq!(lines.next_line().await.unwrap().as_deref(), Some("10"));
+        assert_eq!(server.await.unwrap().unwrap(), Status::Halted);
+        assert_eq!(lines.next_line().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,355 @@
+//! Extra opcodes on top of the built-in instruction set.
+
+use crate::{
+    Computer, Execution, InputSource, Instruction, IntcodeError, Memory, Mode, Observer, Op,
+    OutputSink, Status, Word,
+};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// Called with one value per parameter: read parameters are resolved through their mode like
+/// for built-ins, write parameters are resolved to the address they store to. `None` fails
+/// the instruction with `IntcodeError::ExtensionFailed`.
+///
+/// The handler is only called once every check that could stop the instruction has passed,
+/// so side effects in it happen once per executed instruction. The exception is an effect
+/// that doesn't match the extension, which fails the instruction after the handler ran.
+pub type Handler = Box<dyn FnMut(&[Word]) -> Option<Effect> + Send>;
+
+/// What an extended instruction does besides moving on to the next instruction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Effect {
+    /// One value per write parameter, in order.
+    pub writes: Vec<Word>,
+    pub output: Option<Word>,
+    pub jump: Option<Word>,
+}
+
+impl Effect {
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    pub fn write(values: &[Word]) -> Self {
+        Self {
+            writes: values.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    pub fn output(value: Word) -> Self {
+        Self {
+            output: Some(value),
+            ..Self::default()
+        }
+    }
+
+    pub fn jump(address: Word) -> Self {
+        Self {
+            jump: Some(address),
+            ..Self::default()
+        }
+    }
+}
+
+pub struct Extension {
+    arity: usize,
+    writes: Vec<usize>,
+    output: bool,
+    handler: Handler,
+}
+
+impl Extension {
+    /// An instruction taking `arity` parameters, of which the (zero-based) ones in `writes`
+    /// are stored through.
+    pub fn new<F>(arity: usize, writes: &[usize], handler: F) -> Self
+    where
+        F: FnMut(&[Word]) -> Option<Effect> + Send + 'static,
+    {
+        Self {
+            arity,
+            writes: writes.to_vec(),
+            output: false,
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Allows the handler to output. The output limit is then checked before calling it,
+    /// whether it outputs or not.
+    pub fn outputs(mut self) -> Self {
+        self.output = true;
+        self
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Extensions(HashMap<Word, Extension>);
+
+impl Extensions {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<M: Memory, I: InputSource, O: OutputSink> Computer<M, I, O> {
+    /// Makes `code` (the last two digits of an instruction word) run `extension`. Parameter
+    /// modes are decoded like for built-ins, and the mode digits past the last parameter must
+    /// be zero. Extended instructions count towards the limits and are reported to observers
+    /// as `Op::Extended`.
+    ///
+    /// # Panics
+    ///
+    /// If `code` isn't in `1..100`, is a built-in opcode, or the extension declares more than
+    /// three parameters or a write parameter past its arity.
+    pub fn register(&mut self, code: Word, extension: Extension) {
+        assert!((1..100).contains(&code), "opcode out of range: {}", code);
+        assert!(Op::try_from(code).is_err(), "built-in opcode: {}", code);
+        assert!(extension.arity <= 3, "too many parameters");
+        assert!(
+            extension.writes.iter().all(|&i| i < extension.arity),
+            "write parameter past arity"
+        );
+        self.extensions.0.insert(code, extension);
+    }
+
+    /// Executes the instruction at `ip` if it is an extended one.
+    pub(crate) fn step_extension<T: Observer>(
+        &mut self,
+        ip: Word,
+        word: Word,
+        observer: &mut T,
+    ) -> Option<Result<Option<Status>, IntcodeError>> {
+        if self.extensions.0.is_empty() || !self.extensions.0.contains_key(&(word % 100)) {
+            return None;
+        }
+        Some(self.execute_extension(ip, word, observer))
+    }
+
+    fn execute_extension<T: Observer>(
+        &mut self,
+        ip: Word,
+        word: Word,
+        observer: &mut T,
+    ) -> Result<Option<Status>, IntcodeError> {
+        let extension = &self.extensions.0[&(word % 100)];
+        let (arity, output) = (extension.arity, extension.output);
+        let writes = extension.writes.clone();
+
+        let mut modes = [Mode::Position; 3];
+        let mut operands = [0; 3];
+        let mut targets = Vec::new();
+        let mut digits = word / 100;
+        for i in 0..arity {
+            modes[i] = Mode::try_from(digits % 10).map_err(|e| e.at(ip, word))?;
+            digits /= 10;
+            if writes.contains(&i) {
+                operands[i] = self.target(i, modes[i])?;
+                targets.push(operands[i]);
+            } else {
+                operands[i] = self.arg(i, modes[i])?;
+            }
+        }
+        // Mode digits past the last parameter must be zero.
+        for _ in arity..3 {
+            if digits % 10 != 0 {
+                return Err(IntcodeError::IllegalMode {
+                    ip,
+                    instruction: word,
+                    mode: digits % 10,
+                });
+            }
+            digits /= 10;
+        }
+        self.check_limits(word, output, &targets)?;
+
+        let failed = IntcodeError::ExtensionFailed {
+            ip,
+            instruction: word,
+        };
+        let handler = &mut self.extensions.0.get_mut(&(word % 100)).unwrap().handler;
+        let effect = handler(&operands[..arity]).ok_or(failed)?;
+        if effect.writes.len() != targets.len() || (effect.output.is_some() && !output) {
+            return Err(failed);
+        }
+
+        let instruction = Instruction {
+            op: Op::Extended {
+                code: (word % 100) as u8,
+                arity: arity as u8,
+            },
+            modes,
+        };
+        observer.before(ip, &instruction, &operands[..arity]);
+        let mut execution = Execution {
+            ip,
+            word,
+            instruction,
+            operands,
+            next_ip: effect.jump.unwrap_or(ip + arity as Word + 1),
+            write: None,
+            base: None,
+            input: None,
+            output: effect.output,
+        };
+        for (&address, &value) in targets.iter().zip(&effect.writes) {
+            let write = self.store(address, value)?;
+            execution.write = execution.write.or(Some(write));
+        }
+        if let Some(value) = effect.output {
+            self.outputs.emit(value);
+            self.emitted += 1;
+        }
+        self.ip = execution.next_ip;
+        self.executed += 1;
+        observer.after(&execution);
+        self.forget_history();
+
+        Ok(effect.output.map(Status::Output))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Limit, Limits, Profiler, Replay};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_extensions() {
+        let printed = Arc::new(Mutex::new(Vec::new()));
+        let log = printed.clone();
+
+        let program = vec![
+            1120, 17, 5, 16, // div #17, #5, [16]
+            1121, 17, 5, 17, // mod #17, #5, [17]
+            22, 16, // dbg [16]
+            4, 17, // out [17]
+            1120, 1, 0, 16, // div #1, #0, [16]
+            0, 0,
+        ];
+        let mut computer = Computer::new(&program, &[]);
+        computer.register(
+            20,
+            Extension::new(3, &[2], |p| Some(Effect::write(&[p[0].checked_div(p[1])?]))),
+        );
+        computer.register(
+            21,
+            Extension::new(3, &[2], |p| Some(Effect::write(&[p[0].checked_rem(p[1])?]))),
+        );
+        computer.register(
+            22,
+            Extension::new(1, &[], move |p| {
+                log.lock().unwrap().push(p[0]);
+                Some(Effect::none())
+            }),
+        );
+
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::ExtensionFailed {
+                ip: 12,
+                instruction: 1120
+            })
+        );
+        assert_eq!(computer.outputs(), &[2]);
+        assert_eq!(*printed.lock().unwrap(), vec![3]);
+        assert_eq!(computer.counters().instructions, 4);
+    }
+
+    #[test]
+    fn test_extension_limits() {
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let mut computer = Computer::new(&[32, 32, 99], &[]);
+        computer.register(
+            32,
+            Extension::new(0, &[], move |_| {
+                *counter.lock().unwrap() += 1;
+                Some(Effect::output(7))
+            })
+            .outputs(),
+        );
+        computer.set_limits(Limits::none().outputs(1));
+        for _ in 0..3 {
+            assert!(matches!(
+                computer.run(),
+                Err(IntcodeError::LimitExceeded {
+                    ip: 1,
+                    limit: Limit::Outputs,
+                    ..
+                })
+            ));
+        }
+        assert_eq!(*calls.lock().unwrap(), 1);
+        assert_eq!(computer.outputs(), &[7]);
+
+        let mut computer = Computer::new(&[33, 99], &[]);
+        computer.register(33, Extension::new(0, &[], |_| Some(Effect::output(7))));
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::ExtensionFailed {
+                ip: 0,
+                instruction: 33
+            })
+        );
+    }
+
+    #[test]
+    fn test_extension_observers() {
+        let mut computer = Computer::new(&[134, 5, 6, 99], &[]);
+        computer.register(
+            34,
+            Extension::new(2, &[1], |p| {
+                Some(Effect {
+                    writes: vec![p[0] + 1],
+                    output: Some(p[0]),
+                    jump: None,
+                })
+            })
+            .outputs(),
+        );
+        let mut observers = (Replay::new(&[]), Profiler::new());
+        assert_eq!(computer.run_with(&mut observers), Ok(Status::Halted));
+        let (replay, profiler) = observers;
+        assert_eq!(replay.outputs(), vec![5]);
+        let op = Op::Extended { code: 34, arity: 2 };
+        assert_eq!(profiler.opcode(op), 1);
+        assert!(profiler.report(5).contains("ext34"));
+        assert_eq!(computer.memory()[6], 6);
+    }
+
+    #[test]
+    fn test_extension_modes() {
+        let mut computer = Computer::new(&[11130, 1, 2, 3, 99], &[]);
+        computer.register(30, Extension::new(3, &[2], |_| Some(Effect::write(&[0]))));
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::ImmediateWrite {
+                ip: 0,
+                instruction: 11130
+            })
+        );
+
+        let mut computer = Computer::new(&[90031, 3, 99], &[]);
+        computer.register(31, Extension::new(1, &[], |p| Some(Effect::jump(p[0]))));
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::IllegalMode {
+                ip: 0,
+                instruction: 90031,
+                mode: 9
+            })
+        );
+
+        let mut computer = Computer::new(&[131, 3, 99, 4, 0, 99], &[]);
+        computer.register(31, Extension::new(1, &[], |p| Some(Effect::jump(p[0]))));
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.outputs(), &[131]);
+    }
+
+    #[test]
+    #[should_panic(expected = "built-in opcode: 4")]
+    fn test_builtin_opcode() {
+        Computer::new(&[99], &[]).register(4, Extension::new(0, &[], |_| None));
+    }
+}
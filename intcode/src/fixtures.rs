@@ -0,0 +1,19 @@
+//! Programs shared by the tests.
+
+/// Doubles every input until it reads a 0, then halts.
+pub const DOUBLER: &str = "
+        loop:   in [x]
+                jf [x], #done
+                mul [x], #2, [x]
+                out [x]
+                jt #1, #loop
+        done:   hlt
+        x:      .data 0";
+
+/// Outputs 3, 2 and 1, keeping the count in `n`.
+pub const COUNTDOWN: &str = "
+        loop:   out [n]
+                add [n], #-1, [n]
+                jt [n], #loop
+                hlt
+        n:      .data 3";
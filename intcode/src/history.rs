@@ -0,0 +1,342 @@
+//! Recording execution so a computer can be stepped backwards.
+//!
+//! While recording, every executed instruction is logged with what it changed, and a
+//! checkpoint of the whole state is taken every `interval` instructions. Only the log since
+//! the latest checkpoint is kept: going back further restores an older checkpoint and replays
+//! from there with the inputs consumed since, which rebuilds the log on the way. Memory use is
+//! bounded by `checkpoints` copies of memory plus `interval` log entries.
+
+use crate::{Computer, Execution, InputSource, Memory, OutputSink, Word};
+use std::collections::VecDeque;
+use std::mem;
+
+struct Checkpoint {
+    step: u64,
+    ip: Word,
+    base: Word,
+    halted: bool,
+    outputs: usize,
+    memory: Vec<(usize, Vec<Word>)>,
+    /// Inputs consumed from here up to the next checkpoint.
+    inputs: Vec<Word>,
+}
+
+pub(crate) struct History {
+    interval: u64,
+    capacity: usize,
+    checkpoints: VecDeque<Checkpoint>,
+    /// Executions since the latest checkpoint, up to the current state.
+    log: Vec<Execution>,
+}
+
+impl<M: Memory, I: InputSource, O: OutputSink> Computer<M, I, O> {
+    /// Starts recording, dropping anything recorded before. A checkpoint is taken every
+    /// `interval` instructions and the oldest is dropped once there are more than
+    /// `checkpoints`, which is as far back as the computer can go.
+    ///
+    /// Changes that don't come from executing built-in instructions, like `patch` or extended
+    /// instructions, can't be undone: the former are lost when going back past them, the latter
+    /// drop the recording so far.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` or `checkpoints` is zero.
+    pub fn record(&mut self, interval: u64, checkpoints: usize) {
+        assert!(interval > 0 && checkpoints > 0, "empty recording");
+        self.history = Some(History {
+            interval,
+            capacity: checkpoints,
+            checkpoints: VecDeque::new(),
+            log: Vec::new(),
+        });
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.history = None;
+    }
+
+    pub const fn is_recording(&self) -> bool {
+        self.history.is_some()
+    }
+
+    /// The earliest instruction count the computer can travel back to.
+    pub fn recorded_from(&self) -> Option<u64> {
+        self.history.as_ref()?.checkpoints.front().map(|c| c.step)
+    }
+
+    /// Instructions executed since the latest checkpoint, oldest first.
+    pub fn recent_executions(&self) -> &[Execution] {
+        self.history.as_ref().map_or(&[], |h| &h.log)
+    }
+
+    /// Takes a checkpoint if one is due. Called before every instruction while recording.
+    pub(crate) fn checkpoint_if_due(&mut self) {
+        let due = match &self.history {
+            Some(history) => {
+                history.checkpoints.is_empty() || history.log.len() as u64 >= history.interval
+            }
+            None => false,
+        };
+        if !due {
+            return;
+        }
+
+        let checkpoint = Checkpoint {
+            step: self.executed,
+            ip: self.ip,
+            base: self.base,
+            halted: self.halted,
+            outputs: self.emitted,
+            memory: self
+                .memory
+                .regions()
+                .into_iter()
+                .map(|(start, words)| (start, words.to_vec()))
+                .collect(),
+            inputs: Vec::new(),
+        };
+        let history = self.history.as_mut().unwrap();
+        history.log.clear();
+        history.checkpoints.push_back(checkpoint);
+        if history.checkpoints.len() > history.capacity {
+            history.checkpoints.pop_front();
+        }
+    }
+
+    pub(crate) fn record_execution(&mut self, execution: &Execution) {
+        if let Some(history) = &mut self.history {
+            if let (Some(input), Some(checkpoint)) =
+                (execution.input, history.checkpoints.back_mut())
+            {
+                checkpoint.inputs.push(input);
+            }
+            history.log.push(*execution);
+        }
+    }
+
+    /// Drops everything recorded so far, after a change that can't be undone.
+    pub(crate) fn forget_history(&mut self) {
+        if let Some(history) = &mut self.history {
+            history.checkpoints.clear();
+            history.log.clear();
+        }
+    }
+}
+
+impl<M: Memory + Default> Computer<M> {
+    /// Undoes the last instruction. False if it wasn't recorded.
+    pub fn step_back(&mut self) -> bool {
+        self.executed > 0 && self.travel_to(self.executed - 1)
+    }
+
+    /// Moves to the state after `step` instructions: backwards through the recording, or
+    /// forwards by executing. False if `step` is before the recording, or executing stopped
+    /// short of it.
+    pub fn travel_to(&mut self, step: u64) -> bool {
+        let start = match self.history.as_ref().and_then(|h| h.checkpoints.back()) {
+            Some(checkpoint) => checkpoint.step,
+            None => return false,
+        };
+        if step < start {
+            let history = self.history.as_ref().unwrap();
+            match history.checkpoints.iter().rposition(|c| c.step <= step) {
+                Some(i) => self.restore_checkpoint(i),
+                None => return false,
+            }
+        }
+
+        while self.executed > step {
+            self.undo();
+        }
+        while self.executed < step {
+            let executed = self.executed;
+            if self.step_instruction_with(&mut ()).is_err() || self.executed == executed {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Travels back to the last time the instruction at `address` was about to be executed.
+    /// If it never was, stops at the start of the recording and returns false.
+    pub fn run_back_to(&mut self, address: Word) -> bool {
+        match self.find_back(|e| e.ip == address) {
+            Some((step, _)) => self.travel_to(step),
+            None => {
+                if let Some(start) = self.recorded_from() {
+                    self.travel_to(start);
+                }
+                false
+            }
+        }
+    }
+
+    /// The last recorded instruction that wrote to `address`, with the number of instructions
+    /// executed before it. Looking past the latest checkpoint replays older parts of the
+    /// recording, but the computer ends up where it was.
+    pub fn last_write(&mut self, address: Word) -> Option<(u64, Execution)> {
+        let now = self.executed;
+        let found = self.find_back(|e| e.write.is_some_and(|w| w.address == address));
+        self.travel_to(now);
+        found
+    }
+
+    /// Searches the recording backwards from the current state. Every older segment searched
+    /// is replayed, leaving the computer at the end of the last one.
+    fn find_back<F>(&mut self, matches: F) -> Option<(u64, Execution)>
+    where
+        F: Fn(&Execution) -> bool,
+    {
+        loop {
+            let history = self.history.as_ref()?;
+            let start = history.checkpoints.back()?.step;
+            if let Some(i) = history.log.iter().rposition(&matches) {
+                return Some((start + i as u64, history.log[i]));
+            }
+            let previous = history.checkpoints.len().checked_sub(2)?;
+            self.restore_checkpoint(previous);
+            self.travel_to(start);
+        }
+    }
+
+    /// Reverts the last logged instruction.
+    fn undo(&mut self) {
+        let history = self.history.as_mut().unwrap();
+        let execution = history.log.pop().unwrap();
+        if let Some(input) = execution.input {
+            history.checkpoints.back_mut().unwrap().inputs.pop();
+            self.inputs.push_front(input);
+        }
+        if let Some(write) = execution.write {
+            self.memory_set(write.address, write.old).unwrap();
+        }
+        if let Some((old, _)) = execution.base {
+            self.base = old;
+        }
+        if execution.output.is_some() {
+            self.outputs.pop();
+            self.emitted -= 1;
+        }
+        self.ip = execution.ip;
+        self.halted = false;
+        self.executed -= 1;
+    }
+
+    /// Goes back to the `index`th checkpoint, dropping the ones after it. Inputs consumed
+    /// since are queued again.
+    fn restore_checkpoint(&mut self, index: usize) {
+        let history = self.history.as_mut().unwrap();
+        let later = history.checkpoints.split_off(index + 1);
+        history.log.clear();
+        let checkpoint = history.checkpoints.back_mut().unwrap();
+
+        let mut consumed = mem::take(&mut checkpoint.inputs);
+        for later in later {
+            consumed.extend(later.inputs);
+        }
+        for input in consumed.into_iter().rev() {
+            self.inputs.push_front(input);
+        }
+
+        let mut memory = M::default();
+        for (start, words) in &checkpoint.memory {
+            for (i, &value) in words.iter().enumerate() {
+                memory.write(start + i, value);
+            }
+        }
+        self.memory = memory;
+        self.cache.clear();
+        self.ip = checkpoint.ip;
+        self.base = checkpoint.base;
+        self.halted = checkpoint.halted;
+        self.outputs.truncate(checkpoint.outputs);
+        self.emitted = checkpoint.outputs;
+        self.executed = checkpoint.step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{COUNTDOWN, DOUBLER};
+    use crate::{assemble, load_intcode, Status};
+
+    #[test]
+    fn test_step_back() {
+        let program = assemble(COUNTDOWN).unwrap();
+        let mut computer = Computer::new(&program, &[]);
+        computer.record(4, 4);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.outputs(), &[3, 2, 1]);
+        let end = computer.snapshot();
+
+        assert!(computer.step_back());
+        assert!(computer.step_back());
+        assert_eq!(computer.ip(), 6);
+        assert_eq!(computer.memory()[10], 0);
+        assert!(computer.step_back());
+        assert_eq!(computer.memory()[10], 1);
+        assert_eq!(computer.outputs(), &[3, 2, 1]);
+        assert!(computer.step_back());
+        assert_eq!(computer.outputs(), &[3, 2]);
+
+        // Past the latest checkpoint, and past the start of the recording.
+        assert!(computer.travel_to(1));
+        assert_eq!(computer.outputs(), &[3]);
+        assert_eq!(computer.recorded_from(), Some(0));
+        assert!(computer.step_back());
+        assert_eq!(computer.memory(), &program[..]);
+        assert_eq!(computer.outputs(), &[]);
+        assert!(!computer.step_back());
+
+        assert!(computer.travel_to(end.instructions));
+        assert_eq!(computer.snapshot(), end);
+    }
+
+    #[test]
+    fn test_queries() {
+        let program = assemble(DOUBLER).unwrap();
+        let mut computer = Computer::new(&program, &[1, 2, 0]);
+        computer.record(5, 2);
+        computer.run().unwrap();
+        assert_eq!(computer.recorded_from(), Some(5));
+        assert_eq!(computer.recent_executions().len(), 3);
+
+        let (step, writer) = computer.last_write(15).unwrap();
+        assert_eq!((step, writer.ip, writer.input), (10, 0, Some(0)));
+        assert_eq!(computer.executed, 13);
+        assert_eq!(computer.last_write(0), None);
+
+        assert!(computer.run_back_to(9));
+        assert_eq!(computer.executed, 8);
+        assert_eq!(computer.outputs(), &[2]);
+        assert_eq!(computer.inputs(), &[0]);
+        assert_eq!(computer.memory()[15], 4);
+        let (step, writer) = computer.last_write(15).unwrap();
+        assert_eq!((step, writer.ip, writer.write.unwrap().value), (7, 5, 4));
+
+        // Past the latest checkpoint, which gives back the inputs consumed since.
+        assert!(!computer.run_back_to(99));
+        assert_eq!(computer.executed, 5);
+        assert_eq!(computer.inputs(), &[2, 0]);
+    }
+
+    #[test]
+    fn test_day09_replay() {
+        let program = load_intcode("../day09/input/input.txt");
+        let mut computer = Computer::new(&program, &[1]);
+        computer.record(50, 5);
+        computer.run().unwrap();
+        let end = computer.snapshot();
+
+        // Undoing writes past the end of the program leaves memory grown, but zeroed.
+        assert!(computer.run_back_to(0));
+        assert_eq!(computer.executed, 0);
+        assert_eq!(computer.inputs(), &[1]);
+        assert_eq!(computer.outputs(), &[]);
+        assert_eq!(&computer.memory()[..program.len()], &program[..]);
+        assert!(computer.memory()[program.len()..].iter().all(|&w| w == 0));
+        assert!(computer.travel_to(end.instructions));
+        assert_eq!(computer.snapshot(), end);
+    }
+}
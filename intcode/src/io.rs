@@ -0,0 +1,227 @@
+use crate::Word;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender, SyncSender};
+
+/// Where a `Computer` gets its inputs from.
+pub trait InputSource {
+    /// The next input, or `None` if there is none (for now). A `None` makes the computer report
+    /// `Status::NeedsInput` without consuming anything, so it can be asked again later.
+    fn next_input(&mut self) -> Option<Word>;
+}
+
+/// Where a `Computer` sends its outputs.
+pub trait OutputSink {
+    fn emit(&mut self, value: Word);
+}
+
+/// The default queue behind `Computer::new` and `push_input`.
+impl InputSource for VecDeque<Word> {
+    #[inline]
+    fn next_input(&mut self) -> Option<Word> {
+        self.pop_front()
+    }
+}
+
+/// Blocks until a value arrives. Once every sender is gone the computer sees `NeedsInput`.
+impl InputSource for Receiver<Word> {
+    fn next_input(&mut self) -> Option<Word> {
+        self.recv().ok()
+    }
+}
+
+impl<F: FnMut() -> Option<Word>> InputSource for F {
+    fn next_input(&mut self) -> Option<Word> {
+        self()
+    }
+}
+
+/// Feeds the items of an iterator.
+pub struct IterInput<I>(pub I);
+
+impl<I: Iterator<Item = Word>> InputSource for IterInput<I> {
+    fn next_input(&mut self) -> Option<Word> {
+        self.0.next()
+    }
+}
+
+/// Reads whitespace separated integers from stdin. End of input, or anything that isn't an
+/// integer, leaves the computer waiting for input. A token that isn't an integer stays where it
+/// is, so every later read stops at it too.
+#[derive(Default)]
+pub struct StdinInput {
+    pending: VecDeque<String>,
+}
+
+impl InputSource for StdinInput {
+    fn next_input(&mut self) -> Option<Word> {
+        while self.pending.is_empty() {
+            let mut line = String::new();
+            if io::stdin().lock().read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            self.pending
+                .extend(line.split_whitespace().map(str::to_string));
+        }
+        let value = self.pending.front()?.parse().ok()?;
+        self.pending.pop_front();
+        Some(value)
+    }
+}
+
+/// The default sink behind `Computer::outputs`.
+impl OutputSink for Vec<Word> {
+    #[inline]
+    fn emit(&mut self, value: Word) {
+        self.push(value);
+    }
+}
+
+/// Values sent after the receiver is gone are dropped.
+impl OutputSink for Sender<Word> {
+    fn emit(&mut self, value: Word) {
+        self.send(value).ok();
+    }
+}
+
+/// Blocks while the channel is full. Values sent after the receiver is gone are dropped.
+impl OutputSink for SyncSender<Word> {
+    fn emit(&mut self, value: Word) {
+        self.send(value).ok();
+    }
+}
+
+impl<F: FnMut(Word)> OutputSink for F {
+    fn emit(&mut self, value: Word) {
+        self(value);
+    }
+}
+
+/// Keeps only the most recent `capacity` outputs.
+#[derive(Debug, Clone)]
+pub struct BoundedOutput {
+    capacity: usize,
+    values: VecDeque<Word>,
+    dropped: usize,
+}
+
+impl BoundedOutput {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            values: VecDeque::with_capacity(capacity),
+            dropped: 0,
+        }
+    }
+
+    /// Oldest first.
+    pub const fn values(&self) -> &VecDeque<Word> {
+        &self.values
+    }
+
+    pub fn last(&self) -> Option<Word> {
+        self.values.back().cloned()
+    }
+
+    /// How many outputs were pushed out to stay within capacity.
+    pub const fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+impl OutputSink for BoundedOutput {
+    fn emit(&mut self, value: Word) {
+        if self.values.len() == self.capacity {
+            self.dropped += 1;
+            if self.values.pop_front().is_none() {
+                return;
+            }
+        }
+        self.values.push_back(value);
+    }
+}
+
+/// Prints every output on its own line.
+#[derive(Default)]
+pub struct StdoutOutput;
+
+impl OutputSink for StdoutOutput {
+    fn emit(&mut self, value: Word) {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        writeln!(out, "{}", value).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::DOUBLER;
+    use crate::{assemble, Computer, Status};
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_iterator_and_bounded_output() {
+        let program = assemble(DOUBLER).unwrap();
+        let mut computer = Computer::with_io(
+            program,
+            IterInput(vec![1, 2, 3, 4].into_iter()),
+            BoundedOutput::new(2),
+        );
+        assert_eq!(computer.run(), Ok(Status::NeedsInput));
+        assert_eq!(computer.output().values(), &[6, 8]);
+        assert_eq!(computer.output().dropped(), 2);
+        assert_eq!(computer.counters().outputs, 4);
+    }
+
+    #[test]
+    fn test_channels_and_closures() {
+        let program = assemble(DOUBLER).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let (out_tx, out_rx) = mpsc::channel();
+        tx.send(5).unwrap();
+        tx.send(6).unwrap();
+        drop(tx);
+
+        let mut computer = Computer::with_io(program.clone(), rx, out_tx);
+        assert_eq!(computer.run(), Ok(Status::NeedsInput));
+        assert_eq!(out_rx.try_iter().collect::<Vec<_>>(), vec![10, 12]);
+
+        let mut next = 0;
+        let mut seen = Vec::new();
+        let mut computer = Computer::with_io(
+            program,
+            || {
+                next += 1;
+                if next <= 3 {
+                    Some(next)
+                } else {
+                    None
+                }
+            },
+            |value| seen.push(value),
+        );
+        assert_eq!(computer.run(), Ok(Status::NeedsInput));
+        drop(computer);
+        assert_eq!(seen, vec![2, 4, 6]);
+    }
+
+    #[test]
+    fn test_missing_input_consumes_nothing() {
+        let mut computer = Computer::new(&assemble(DOUBLER).unwrap(), &[]);
+        assert_eq!(computer.run(), Ok(Status::NeedsInput));
+        assert_eq!(computer.ip(), 0);
+        assert_eq!(computer.counters().instructions, 0);
+        computer.push_input(4);
+        computer.push_input(0);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.outputs(), &[8]);
+    }
+
+    #[test]
+    fn test_failed_input_consumes_nothing() {
+        let mut computer = Computer::new(&assemble("in [-1]").unwrap(), &[7]);
+        assert!(computer.run().is_err());
+        assert_eq!(computer.inputs(), &[7]);
+    }
+}
@@ -1,7 +1,224 @@
+use cache::DecodeCache;
+use extension::Extensions;
+use history::History;
+use std::collections::VecDeque;
 use std::convert::{TryFrom, TryInto};
-use std::fs;
+use std::error::Error;
+use std::fmt;
 
-enum Op {
+mod analysis;
+mod ascii;
+mod asm;
+mod cache;
+mod disasm;
+#[cfg(feature = "async")]
+mod driver;
+mod extension;
+#[cfg(test)]
+mod fixtures;
+mod history;
+mod io;
+mod limits;
+mod load;
+mod memory;
+mod network;
+mod observer;
+mod patch;
+mod profile;
+mod replay;
+mod snapshot;
+mod symbolic;
+mod threads;
+mod transpile;
+mod word;
+
+pub use analysis::{Analysis, Block, Class, CodeWrite};
+pub use ascii::{terminal, terminal_with, AsciiOutput};
+pub use asm::{assemble, AsmError};
+pub use disasm::{decode_at, disassemble, listing, Item, Line, Operand};
+#[cfg(feature = "tokio")]
+pub use driver::serve;
+#[cfg(feature = "async")]
+pub use driver::AsyncError;
+pub use extension::{Effect, Extension, Handler};
+pub use io::{BoundedOutput, InputSource, IterInput, OutputSink, StdinInput, StdoutOutput};
+pub use limits::{Counters, Limit, Limits};
+pub use load::{
+    decode_binary, encode_binary, parse_intcode, read_intcode, save_binary, try_load_intcode,
+    write_binary, LoadError, BINARY_MAGIC,
+};
+pub use memory::{Memory, PagedMemory, PAGE_SIZE};
+pub use network::{
+    Control, Monitor, Nat, Network, NodeError, Packet, Stop, DEFAULT_MONITOR_ADDRESS,
+};
+pub use observer::{Execution, JsonTracer, MemoryWrite, Observer, RingTracer};
+pub use patch::{Patch, PatchError};
+pub use profile::Profiler;
+pub use replay::{program_hash, Divergence, Event, Replay, ReplayError, REPLAY_VERSION};
+pub use snapshot::{Snapshot, SnapshotError, SNAPSHOT_VERSION};
+pub use symbolic::{Affine, NotAffine, Solver};
+pub use threads::{Fanout, Finished, Pipeline, Running, ThreadedComputer};
+pub use transpile::transpile;
+pub use word::{Arithmetic, Word};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntcodeError {
+    IllegalOpcode {
+        ip: Word,
+        instruction: Word,
+    },
+    IllegalMode {
+        ip: Word,
+        instruction: Word,
+        mode: Word,
+    },
+    NegativeAddress {
+        ip: Word,
+        instruction: Word,
+        address: Word,
+    },
+    ImmediateWrite {
+        ip: Word,
+        instruction: Word,
+    },
+    IpOutOfRange {
+        ip: Word,
+    },
+    /// Executing the instruction at `ip` would exceed `limit`. Nothing was executed, so the
+    /// computer can be resumed after raising its limits.
+    LimitExceeded {
+        ip: Word,
+        instruction: Word,
+        limit: Limit,
+        counters: Counters,
+    },
+    /// The handler of an extended instruction gave up, or returned the wrong number of writes.
+    ExtensionFailed {
+        ip: Word,
+        instruction: Word,
+    },
+    /// A result or relative address didn't fit in a `Word` under `Arithmetic::Checked`.
+    /// Nothing was executed.
+    Overflow {
+        ip: Word,
+        instruction: Word,
+    },
+}
+
+impl IntcodeError {
+    pub const fn ip(&self) -> Word {
+        match *self {
+            Self::IllegalOpcode { ip, .. }
+            | Self::IllegalMode { ip, .. }
+            | Self::NegativeAddress { ip, .. }
+            | Self::ImmediateWrite { ip, .. }
+            | Self::IpOutOfRange { ip }
+            | Self::LimitExceeded { ip, .. }
+            | Self::ExtensionFailed { ip, .. }
+            | Self::Overflow { ip, .. } => ip,
+        }
+    }
+
+    /// The raw instruction word at `ip`, if `ip` pointed into memory at all.
+    pub const fn instruction(&self) -> Option<Word> {
+        match *self {
+            Self::IllegalOpcode { instruction, .. }
+            | Self::IllegalMode { instruction, .. }
+            | Self::NegativeAddress { instruction, .. }
+            | Self::ImmediateWrite { instruction, .. }
+            | Self::LimitExceeded { instruction, .. }
+            | Self::ExtensionFailed { instruction, .. }
+            | Self::Overflow { instruction, .. } => Some(instruction),
+            Self::IpOutOfRange { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for IntcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::IllegalOpcode { ip, instruction } => write!(
+                f,
+                "illegal operation code {} at {} (instruction {})",
+                instruction % 100,
+                ip,
+                instruction
+            ),
+            Self::IllegalMode {
+                ip,
+                instruction,
+                mode,
+            } => write!(
+                f,
+                "illegal argument mode {} at {} (instruction {})",
+                mode, ip, instruction
+            ),
+            Self::NegativeAddress {
+                ip,
+                instruction,
+                address,
+            } => write!(
+                f,
+                "negative address {} at {} (instruction {})",
+                address, ip, instruction
+            ),
+            Self::ImmediateWrite { ip, instruction } => write!(
+                f,
+                "immediate mode illegal for storing values at {} (instruction {})",
+                ip, instruction
+            ),
+            Self::IpOutOfRange { ip } => write!(f, "instruction pointer out of range: {}", ip),
+            Self::LimitExceeded {
+                ip,
+                instruction,
+                limit,
+                counters,
+            } => write!(
+                f,
+                "{} limit exceeded at {} (instruction {}) after {}",
+                limit, ip, instruction, counters
+            ),
+            Self::ExtensionFailed { ip, instruction } => write!(
+                f,
+                "extended operation code {} failed at {} (instruction {})",
+                instruction % 100,
+                ip,
+                instruction
+            ),
+            Self::Overflow { ip, instruction } => write!(
+                f,
+                "arithmetic overflow at {} (instruction {})",
+                ip, instruction
+            ),
+        }
+    }
+}
+
+impl Error for IntcodeError {}
+
+/// Why a single instruction word failed to decode. `Computer` turns this into an
+/// `IntcodeError` once it knows where the word came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    IllegalOpcode,
+    IllegalMode(Word),
+}
+
+impl DecodeError {
+    const fn at(self, ip: Word, instruction: Word) -> IntcodeError {
+        match self {
+            Self::IllegalOpcode => IntcodeError::IllegalOpcode { ip, instruction },
+            Self::IllegalMode(mode) => IntcodeError::IllegalMode {
+                ip,
+                instruction,
+                mode,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
     Add,
     Multiply,
     Read,
@@ -12,12 +229,19 @@ enum Op {
     Equals,
     AdjustBase,
     Halt,
+    /// An instruction registered with `Computer::register`. Never decoded from a word, only
+    /// reported to observers.
+    Extended {
+        code: u8,
+        arity: u8,
+    },
 }
 
-impl TryFrom<i64> for Op {
-    type Error = String;
+impl TryFrom<Word> for Op {
+    type Error = DecodeError;
 
-    fn try_from(n: i64) -> Result<Self, Self::Error> {
+    #[inline]
+    fn try_from(n: Word) -> Result<Self, Self::Error> {
         match n {
             1 => Ok(Self::Add),
             2 => Ok(Self::Multiply),
@@ -29,69 +253,315 @@ impl TryFrom<i64> for Op {
             8 => Ok(Self::Equals),
             9 => Ok(Self::AdjustBase),
             99 => Ok(Self::Halt),
-            _ => Err(format!("illegal operation code: {}", n)),
+            _ => Err(DecodeError::IllegalOpcode),
         }
     }
 }
 
-#[derive(Clone, Copy)]
-enum Mode {
+impl Op {
+    pub const fn code(self) -> Word {
+        match self {
+            Self::Add => 1,
+            Self::Multiply => 2,
+            Self::Read => 3,
+            Self::Write => 4,
+            Self::JumpIfTrue => 5,
+            Self::JumpIfFalse => 6,
+            Self::LessThan => 7,
+            Self::Equals => 8,
+            Self::AdjustBase => 9,
+            Self::Halt => 99,
+            Self::Extended { code, .. } => code as Word,
+        }
+    }
+
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Multiply => "mul",
+            Self::Read => "in",
+            Self::Write => "out",
+            Self::JumpIfTrue => "jt",
+            Self::JumpIfFalse => "jf",
+            Self::LessThan => "lt",
+            Self::Equals => "eq",
+            Self::AdjustBase => "arb",
+            Self::Halt => "hlt",
+            Self::Extended { .. } => "ext",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<Self> {
+        match mnemonic {
+            "add" => Some(Self::Add),
+            "mul" => Some(Self::Multiply),
+            "in" => Some(Self::Read),
+            "out" => Some(Self::Write),
+            "jt" => Some(Self::JumpIfTrue),
+            "jf" => Some(Self::JumpIfFalse),
+            "lt" => Some(Self::LessThan),
+            "eq" => Some(Self::Equals),
+            "arb" => Some(Self::AdjustBase),
+            "hlt" => Some(Self::Halt),
+            _ => None,
+        }
+    }
+
+    /// Number of parameters following the opcode word.
+    #[inline]
+    pub const fn arity(self) -> usize {
+        match self {
+            Self::Add | Self::Multiply | Self::LessThan | Self::Equals => 3,
+            Self::JumpIfTrue | Self::JumpIfFalse => 2,
+            Self::Read | Self::Write | Self::AdjustBase => 1,
+            Self::Halt => 0,
+            Self::Extended { arity, .. } => arity as usize,
+        }
+    }
+
+    /// Index of the parameter the instruction stores its result through, if any.
+    #[inline]
+    pub const fn write_param(self) -> Option<usize> {
+        match self {
+            Self::Add | Self::Multiply | Self::LessThan | Self::Equals => Some(2),
+            Self::Read => Some(0),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
     Position,
     Immediate,
     Relative,
 }
 
-impl TryFrom<i64> for Mode {
-    type Error = String;
+impl TryFrom<Word> for Mode {
+    type Error = DecodeError;
 
-    fn try_from(n: i64) -> Result<Self, Self::Error> {
+    #[inline]
+    fn try_from(n: Word) -> Result<Self, Self::Error> {
         match n {
             0 => Ok(Self::Position),
             1 => Ok(Self::Immediate),
             2 => Ok(Self::Relative),
-            _ => Err(format!("illegal argument mode: {}", n)),
+            _ => Err(DecodeError::IllegalMode(n)),
         }
     }
 }
 
-struct Instruction {
-    op: Op,
-    modes: [Mode; 3],
+impl Mode {
+    pub const fn digit(self) -> Word {
+        match self {
+            Self::Position => 0,
+            Self::Immediate => 1,
+            Self::Relative => 2,
+        }
+    }
 }
 
-impl TryFrom<i64> for Instruction {
-    type Error = String;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub op: Op,
+    pub modes: [Mode; 3],
+}
 
-    fn try_from(n: i64) -> Result<Self, Self::Error> {
-        let op = (n % 100).try_into()?;
-        let mut n = n / 100;
-        let mut modes = [Mode::Position; 3];
+impl TryFrom<Word> for Instruction {
+    type Error = DecodeError;
 
-        for i in 0..3 {
-            modes[i] = (n % 10).try_into()?;
-            n /= 10;
+    #[inline(always)]
+    fn try_from(n: Word) -> Result<Self, Self::Error> {
+        // Every valid instruction word fits in a `u32`, and dividing one is a lot cheaper.
+        if let Ok(n) = u32::try_from(n) {
+            let op = Word::from(n % 100).try_into()?;
+            let modes = n / 100;
+            let mode = |digits: u32| Mode::try_from(Word::from(digits % 10));
+            return Ok(Self {
+                op,
+                modes: [mode(modes)?, mode(modes / 10)?, mode(modes / 100)?],
+            });
         }
+        let op = (n % 100).try_into()?;
+        let modes = n / 100;
+        let mode = |digits: Word| Mode::try_from(digits % 10);
+        Ok(Self {
+            op,
+            modes: [mode(modes)?, mode(modes / 10)?, mode(modes / 100)?],
+        })
+    }
+}
 
-        Ok(Self { op, modes })
+impl Instruction {
+    /// The instruction word that decodes back into `self`.
+    pub fn encode(&self) -> Word {
+        self.modes
+            .iter()
+            .rev()
+            .fold(0, |word, mode| word * 10 + mode.digit())
+            * 100
+            + self.op.code()
     }
 }
 
+/// Why `Computer::step` or `Computer::run` handed control back to the caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Output(Word),
+    /// The computer is parked on a read with an empty input queue. `ip` still points at the
+    /// read, so pushing an input and stepping again resumes it.
+    NeedsInput,
+    Halted,
+}
+
 #[derive(Default)]
-pub struct Computer {
-    memory: Vec<i64>,
-    inputs: Vec<i64>,
-    outputs: Vec<i64>,
-    ip: i64,
+pub struct Computer<M = Vec<Word>, I = VecDeque<Word>, O = Vec<Word>> {
+    memory: M,
+    inputs: I,
+    outputs: O,
+    ip: Word,
     halted: bool,
-    base: i64,
+    base: Word,
+    limits: Limits,
+    executed: u64,
+    emitted: usize,
+    cache: DecodeCache,
+    extensions: Extensions,
+    arithmetic: Arithmetic,
+    history: Option<History>,
 }
 
 impl Computer {
-    pub fn new(intcode: &[i64], inputs: &[i64]) -> Self {
-        Self {
-            memory: intcode.to_vec(),
-            inputs: inputs.to_vec(),
+    pub fn new(intcode: &[Word], inputs: &[Word]) -> Self {
+        Self::with_memory(intcode.to_vec(), inputs)
+    }
+
+    pub fn memory(&self) -> &[Word] {
+        &self.memory
+    }
+}
+
+impl<M: Memory + Default> Computer<M> {
+    pub fn with_memory(memory: M, inputs: &[Word]) -> Self {
+        Self::with_io(memory, inputs.iter().cloned().collect(), Vec::new())
+    }
+
+    /// Rebuilds a computer from `snapshot`. Limits aren't part of a snapshot and start out
+    /// unlimited.
+    ///
+    /// # Panics
+    ///
+    /// If a memory region runs past the end of the address space. Use `try_restore` for
+    /// snapshots from untrusted files.
+    pub fn restore(snapshot: &Snapshot) -> Self {
+        Self::try_restore(snapshot, Limits::none())
+            .expect("snapshot memory past the end of the address space")
+    }
+
+    /// Rebuilds a computer from `snapshot` with `limits` in place. If its memory runs past
+    /// the end of the address space or wouldn't fit the memory limit, fails with
+    /// `IntcodeError::LimitExceeded` before allocating it.
+    pub fn try_restore(snapshot: &Snapshot, limits: Limits) -> Result<Self, IntcodeError> {
+        let exceeded = |memory: usize| IntcodeError::LimitExceeded {
+            ip: snapshot.ip,
+            instruction: snapshot.word(snapshot.ip),
+            limit: Limit::Memory,
+            counters: Counters {
+                instructions: snapshot.instructions,
+                memory,
+                outputs: snapshot.outputs.len(),
+            },
+        };
+        let max = limits.memory.unwrap_or(usize::MAX);
+
+        let mut memory = M::default();
+        for (start, words) in &snapshot.memory {
+            let fits = start
+                .checked_add(words.len())
+                .is_some_and(|end| Word::try_from(end).is_ok());
+            if !fits {
+                return Err(exceeded(usize::MAX));
+            }
+            for (i, &value) in words.iter().enumerate() {
+                let address = start + i;
+                let footprint = memory.footprint().saturating_add(memory.growth(address));
+                if footprint > max {
+                    return Err(exceeded(footprint));
+                }
+                memory.write(address, value);
+            }
+        }
+        Ok(Self {
+            memory,
+            inputs: snapshot.inputs.iter().cloned().collect(),
+            outputs: snapshot.outputs.clone(),
+            ip: snapshot.ip,
+            halted: snapshot.halted,
+            base: snapshot.base,
+            limits,
+            executed: snapshot.instructions,
+            emitted: snapshot.outputs.len(),
             ..Self::default()
+        })
+    }
+}
+
+impl<M: Memory> Computer<M> {
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            ip: self.ip,
+            base: self.base,
+            halted: self.halted,
+            instructions: self.executed,
+            inputs: self.inputs.iter().cloned().collect(),
+            outputs: self.outputs.clone(),
+            memory: self
+                .memory
+                .regions()
+                .into_iter()
+                .map(|(start, words)| (start, words.to_vec()))
+                .collect(),
+        }
+    }
+}
+
+impl<M, O> Computer<M, VecDeque<Word>, O> {
+    pub fn push_input(&mut self, input: Word) {
+        self.inputs.push_back(input);
+    }
+
+    /// Inputs queued but not yet consumed.
+    pub const fn inputs(&self) -> &VecDeque<Word> {
+        &self.inputs
+    }
+}
+
+impl<M, I> Computer<M, I, Vec<Word>> {
+    pub fn outputs(&self) -> &[Word] {
+        &self.outputs
+    }
+
+    pub fn last_output(&self) -> Option<Word> {
+        self.outputs.last().cloned()
+    }
+}
+
+impl<M: Memory, I: InputSource, O: OutputSink> Computer<M, I, O> {
+    pub fn with_io(memory: M, inputs: I, outputs: O) -> Self {
+        Self {
+            memory,
+            inputs,
+            outputs,
+            ip: 0,
+            halted: false,
+            base: 0,
+            limits: Limits::default(),
+            executed: 0,
+            emitted: 0,
+            cache: DecodeCache::default(),
+            extensions: Extensions::default(),
+            arithmetic: Arithmetic::default(),
+            history: None,
         }
     }
 
@@ -99,157 +569,413 @@ impl Computer {
         self.halted
     }
 
-    pub fn push_input(&mut self, input: i64) {
-        self.inputs.push(input);
+    pub const fn ip(&self) -> Word {
+        self.ip
     }
 
-    pub fn memory(&self) -> &[i64] {
-        &self.memory
+    pub const fn base(&self) -> Word {
+        self.base
     }
 
-    pub fn outputs(&self) -> &[i64] {
+    pub const fn input(&self) -> &I {
+        &self.inputs
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.inputs
+    }
+
+    pub const fn output(&self) -> &O {
         &self.outputs
     }
 
-    pub fn last_output(&self) -> Option<i64> {
-        self.outputs.last().cloned()
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.outputs
     }
 
-    pub fn patch(&mut self, patch: (i64, i64)) {
-        self.memory[1] = patch.0;
-        self.memory[2] = patch.1;
+    pub const fn limits(&self) -> Limits {
+        self.limits
     }
 
-    pub fn get_patch(&self) -> (i64, i64) {
-        (self.memory[1], self.memory[2])
+    /// Replaces the limits. Raising them lets a computer stopped by `LimitExceeded` continue.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
     }
 
-    pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        while !self.halted {
-            self.step()?;
+    pub fn counters(&self) -> Counters {
+        Counters {
+            instructions: self.executed,
+            memory: self.memory.footprint(),
+            outputs: self.emitted,
         }
-        Ok(())
     }
 
-    pub fn step(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Value of any memory cell, whether or not it has been allocated.
+    pub fn peek(&self, address: usize) -> Word {
+        self.memory.read(address)
+    }
+
+    pub fn memory_regions(&self) -> Vec<(usize, &[Word])> {
+        self.memory.regions()
+    }
+
+    pub fn memory_footprint(&self) -> usize {
+        self.memory.footprint()
+    }
+
+    /// Decoded instructions are cached by address and dropped again when their opcode word is
+    /// written to. The cache only fills once as many instructions have run as the program has
+    /// words, so short runs cost the same either way; on day 9 in the `decode` benchmark,
+    /// turning it off takes about twice as long.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.cache.set_enabled(enabled);
+    }
+
+    pub const fn decode_cache(&self) -> bool {
+        self.cache.enabled()
+    }
+
+    /// What happens when a result doesn't fit in a `Word`. Checked by default.
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    pub const fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// Runs until the computer halts or needs more input, collecting outputs on the way.
+    pub fn run(&mut self) -> Result<Status, IntcodeError> {
+        self.run_with(&mut ())
+    }
+
+    pub fn run_with<T: Observer>(&mut self, observer: &mut T) -> Result<Status, IntcodeError> {
         loop {
-            let instr = Instruction::try_from(self.memory[usize::try_from(self.ip)?])?;
-            match instr.op {
-                Op::Add => {
-                    let x = self.arg(1, instr.modes[0]);
-                    let y = self.arg(2, instr.modes[1]);
-                    self.put(3, x + y, instr.modes[2]);
-                    self.ip += 4;
-                }
-                Op::Multiply => {
-                    let x = self.arg(1, instr.modes[0]);
-                    let y = self.arg(2, instr.modes[1]);
-                    self.put(3, x * y, instr.modes[2]);
-                    self.ip += 4;
+            match self.step_with(observer)? {
+                Status::Output(_) => continue,
+                status => return Ok(status),
+            }
+        }
+    }
+
+    /// Runs until the next output, or until the computer halts or needs more input.
+    pub fn step(&mut self) -> Result<Status, IntcodeError> {
+        self.step_with(&mut ())
+    }
+
+    pub fn step_with<T: Observer>(&mut self, observer: &mut T) -> Result<Status, IntcodeError> {
+        // Nothing executed can set limits, start recording or register extensions, so without
+        // any of them the loop can leave out checking for them.
+        if self.limits == Limits::none() && self.history.is_none() && self.extensions.is_empty() {
+            self.step_loop::<T, false>(observer)
+        } else {
+            self.step_loop::<T, true>(observer)
+        }
+    }
+
+    fn step_loop<T: Observer, const CHECKED: bool>(
+        &mut self,
+        observer: &mut T,
+    ) -> Result<Status, IntcodeError> {
+        loop {
+            if let Some(status) = self.execute::<T, CHECKED>(observer)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    /// Executes exactly one instruction. Returns `None` unless that instruction produced an
+    /// output or halted, or it is a read that can't proceed (in which case nothing is executed).
+    pub fn step_instruction(&mut self) -> Result<Option<Status>, IntcodeError> {
+        self.step_instruction_with(&mut ())
+    }
+
+    pub fn step_instruction_with<T: Observer>(
+        &mut self,
+        observer: &mut T,
+    ) -> Result<Option<Status>, IntcodeError> {
+        self.execute::<T, true>(observer)
+    }
+
+    /// Executes one instruction, leaving out the checks for limits, recording and extensions
+    /// unless `CHECKED`. Always inlined, so the instruction loops get their own copy.
+    #[inline(always)]
+    fn execute<T: Observer, const CHECKED: bool>(
+        &mut self,
+        observer: &mut T,
+    ) -> Result<Option<Status>, IntcodeError> {
+        if self.halted {
+            return Ok(Some(Status::Halted));
+        }
+        if CHECKED && self.history.is_some() {
+            self.checkpoint_if_due();
+        }
+        let ip = self.ip;
+        let word = self.fetch()?;
+        if CHECKED {
+            if let Some(result) = self.step_extension(ip, word, observer) {
+                return result;
+            }
+        }
+        let instruction = match self.cache.get(ip as usize) {
+            Some(instruction) => instruction,
+            None => {
+                let instruction = Instruction::try_from(word).map_err(|e| e.at(ip, word))?;
+                // Until as many instructions have run as the program has words, some may not
+                // have repeated yet, and short runs that never repeat any don't pay for a cache.
+                if self.executed >= self.memory.footprint() as u64 {
+                    self.cache.insert(ip as usize, instruction);
                 }
-                Op::Read => {
-                    let input = self.inputs.remove(0);
-                    self.put(1, input, instr.modes[0]);
-                    self.ip += 2;
+                instruction
+            }
+        };
+        let op = instruction.op;
+        let [m0, m1, m2] = instruction.modes;
+        let mut execution = Execution {
+            ip,
+            word,
+            instruction,
+            operands: [0; 3],
+            next_ip: ip + op.arity() as Word + 1,
+            write: None,
+            base: None,
+            input: None,
+            output: None,
+        };
+
+        // One arm per instruction, each resolving its operands, checking the limits and
+        // anything else that can fail, and only then executing it.
+        let status = match op {
+            Op::Add | Op::Multiply | Op::LessThan | Op::Equals => {
+                let operands = [self.arg(0, m0)?, self.arg(1, m1)?, self.target(2, m2)?];
+                if CHECKED {
+                    self.check_limits(word, false, &operands[2..])?;
                 }
-                Op::Write => {
-                    let x = self.arg(1, instr.modes[0]);
-                    self.outputs.push(x);
-                    self.ip += 2;
-                    break;
+                let [x, y, z] = operands;
+                let value = match op {
+                    Op::Add => self.arithmetic.add(x, y),
+                    Op::Multiply => self.arithmetic.mul(x, y),
+                    Op::LessThan => Some((x < y) as Word),
+                    _ => Some((x == y) as Word),
                 }
-                Op::JumpIfTrue => {
-                    if self.arg(1, instr.modes[0]) == 0 {
-                        self.ip += 3;
-                    } else {
-                        self.ip = self.arg(2, instr.modes[1]);
-                    }
+                .ok_or(IntcodeError::Overflow {
+                    ip,
+                    instruction: word,
+                })?;
+                observer.before(ip, &instruction, &operands);
+                execution.operands = operands;
+                execution.write = Some(self.store(z, value)?);
+                None
+            }
+            Op::Read => {
+                let target = self.target(0, m0)?;
+                if CHECKED {
+                    self.check_limits(word, false, &[target])?;
                 }
-                Op::JumpIfFalse => {
-                    if self.arg(1, instr.modes[0]) == 0 {
-                        self.ip = self.arg(2, instr.modes[1]);
-                    } else {
-                        self.ip += 3;
-                    }
+                // Nothing below may fail once an input has been taken from the source, so
+                // validate the target first and report a missing input without executing
+                // anything.
+                self.address(target)?;
+                let input = match self.inputs.next_input() {
+                    Some(input) => input,
+                    None => return Ok(Some(Status::NeedsInput)),
+                };
+                observer.before(ip, &instruction, &[target]);
+                execution.operands[0] = target;
+                execution.write = Some(self.store(target, input)?);
+                execution.input = Some(input);
+                None
+            }
+            Op::Write => {
+                let x = self.arg(0, m0)?;
+                if CHECKED {
+                    self.check_limits(word, true, &[])?;
                 }
-                Op::LessThan => {
-                    if self.arg(1, instr.modes[0]) < self.arg(2, instr.modes[1]) {
-                        self.put(3, 1, instr.modes[2]);
-                    } else {
-                        self.put(3, 0, instr.modes[2]);
-                    }
-                    self.ip += 4;
+                observer.before(ip, &instruction, &[x]);
+                execution.operands[0] = x;
+                self.outputs.emit(x);
+                self.emitted += 1;
+                execution.output = Some(x);
+                Some(Status::Output(x))
+            }
+            Op::JumpIfTrue | Op::JumpIfFalse => {
+                let operands = [self.arg(0, m0)?, self.arg(1, m1)?];
+                if CHECKED {
+                    self.check_limits(word, false, &[])?;
                 }
-                Op::Equals => {
-                    if self.arg(1, instr.modes[0]) == self.arg(2, instr.modes[1]) {
-                        self.put(3, 1, instr.modes[2]);
-                    } else {
-                        self.put(3, 0, instr.modes[2]);
-                    }
-                    self.ip += 4;
+                observer.before(ip, &instruction, &operands);
+                let [x, y] = operands;
+                if (x != 0) == (op == Op::JumpIfTrue) {
+                    execution.next_ip = y;
                 }
-                Op::AdjustBase => {
-                    self.base += self.arg(1, instr.modes[0]);
-                    self.ip += 2;
+                execution.operands = [x, y, 0];
+                None
+            }
+            Op::AdjustBase => {
+                let x = self.arg(0, m0)?;
+                if CHECKED {
+                    self.check_limits(word, false, &[])?;
                 }
-                Op::Halt => {
-                    self.halted = true;
-                    break;
+                let base = self
+                    .arithmetic
+                    .add(self.base, x)
+                    .ok_or(IntcodeError::Overflow {
+                        ip,
+                        instruction: word,
+                    })?;
+                observer.before(ip, &instruction, &[x]);
+                execution.operands[0] = x;
+                execution.base = Some((self.base, base));
+                self.base = base;
+                None
+            }
+            Op::Halt => {
+                if CHECKED {
+                    self.check_limits(word, false, &[])?;
                 }
+                observer.before(ip, &instruction, &[]);
+                self.halted = true;
+                execution.next_ip = ip;
+                Some(Status::Halted)
             }
+            Op::Extended { .. } => unreachable!("decoded as a built-in"),
+        };
+        self.ip = execution.next_ip;
+        self.executed += 1;
+        observer.after(&execution);
+        if CHECKED && self.history.is_some() {
+            self.record_execution(&execution);
         }
+        Ok(status)
+    }
 
-        Ok(())
+    /// Whether executing an instruction that may output and stores to `targets` would exceed a
+    /// limit.
+    fn check_limits(&self, word: Word, output: bool, targets: &[Word]) -> Result<(), IntcodeError> {
+        let limits = &self.limits;
+        let exceeded = if limits.instructions.is_some_and(|max| self.executed >= max) {
+            Some(Limit::Instructions)
+        } else if output && limits.outputs.is_some_and(|max| self.emitted >= max) {
+            Some(Limit::Outputs)
+        } else if let Some(max) = limits.memory {
+            let mut growth = 0;
+            for &target in targets {
+                growth += self.memory.growth(self.address(target)?);
+            }
+            if self.memory.footprint() + growth > max {
+                Some(Limit::Memory)
+            } else {
+                None
+            }
+        } else {
+            None
+        };
+
+        match exceeded {
+            Some(limit) => Err(IntcodeError::LimitExceeded {
+                ip: self.ip,
+                instruction: word,
+                limit,
+                counters: self.counters(),
+            }),
+            None => Ok(()),
+        }
     }
 
-    fn arg(&mut self, arg_index: i64, mode: Mode) -> i64 {
-        let value = self.memory_get(self.ip + arg_index);
+    fn fetch(&self) -> Result<Word, IntcodeError> {
+        match usize::try_from(self.ip) {
+            Ok(ip) if self.memory.contains(ip) => Ok(self.memory.read(ip)),
+            _ => Err(IntcodeError::IpOutOfRange { ip: self.ip }),
+        }
+    }
+
+    /// Raw `param`th (zero-based) parameter word of the current instruction. The opcode word
+    /// was fetched from `ip`, so its address can't be negative.
+    fn param(&self, param: usize) -> Word {
+        self.memory.read(self.ip as usize + param + 1)
+    }
+
+    /// Value of the `param`th (zero-based) parameter of the current instruction.
+    fn arg(&self, param: usize, mode: Mode) -> Result<Word, IntcodeError> {
+        let value = self.param(param);
         match mode {
             Mode::Position => self.memory_get(value),
-            Mode::Immediate => value,
-            Mode::Relative => self.memory_get(self.base + value),
+            Mode::Immediate => Ok(value),
+            Mode::Relative => self.memory_get(self.relative(value)?),
         }
     }
 
-    fn put(&mut self, index: i64, value: i64, mode: Mode) {
-        let dest = self.memory_get(self.ip + index);
+    /// Address the `param`th (zero-based) parameter of the current instruction stores to.
+    fn target(&self, param: usize, mode: Mode) -> Result<Word, IntcodeError> {
+        let value = self.param(param);
         match mode {
-            Mode::Position => self.memory_set(dest, value),
-            Mode::Immediate => panic!("immediate mode illegal for storing values"),
-            Mode::Relative => self.memory_set(self.base + dest, value),
-        };
+            Mode::Position => Ok(value),
+            Mode::Immediate => Err(IntcodeError::ImmediateWrite {
+                ip: self.ip,
+                instruction: self.memory_get(self.ip)?,
+            }),
+            Mode::Relative => self.relative(value),
+        }
     }
 
-    fn memory_get(&mut self, index: i64) -> i64 {
-        let index: usize = index.try_into().unwrap();
-        if self.memory.len() < index + 1 {
-            self.memory.resize(index + 1, 0);
+    fn relative(&self, offset: Word) -> Result<Word, IntcodeError> {
+        match self.arithmetic.add(self.base, offset) {
+            Some(address) => Ok(address),
+            None => Err(IntcodeError::Overflow {
+                ip: self.ip,
+                instruction: self.memory_get(self.ip)?,
+            }),
         }
-        self.memory[index]
     }
 
-    fn memory_set(&mut self, index: i64, value: i64) {
-        let index: usize = index.try_into().unwrap();
-        if self.memory.len() < index + 1 {
-            self.memory.resize(index + 1, 0);
+    fn store(&mut self, address: Word, value: Word) -> Result<MemoryWrite, IntcodeError> {
+        let index = self.address(address)?;
+        let old = self.memory.read(index);
+        self.memory.write(index, value);
+        self.cache.invalidate(index);
+        Ok(MemoryWrite {
+            address,
+            old,
+            value,
+        })
+    }
+
+    /// Reads outside of the loaded program yield 0 without growing memory.
+    fn memory_get(&self, index: Word) -> Result<Word, IntcodeError> {
+        let index = self.address(index)?;
+        Ok(self.memory.read(index))
+    }
+
+    fn memory_set(&mut self, index: Word, value: Word) -> Result<(), IntcodeError> {
+        let index = self.address(index)?;
+        self.memory.write(index, value);
+        self.cache.invalidate(index);
+        Ok(())
+    }
+
+    fn address(&self, index: Word) -> Result<usize, IntcodeError> {
+        usize::try_from(index).map_err(|_| self.negative_address(index))
+    }
+
+    #[cold]
+    fn negative_address(&self, index: Word) -> IntcodeError {
+        IntcodeError::NegativeAddress {
+            ip: self.ip,
+            instruction: self.memory.read(self.ip as usize),
+            address: index,
         }
-        self.memory[index] = value;
     }
 }
 
-pub fn run_intcode(intcode: &[i64], inputs: &[i64]) -> Computer {
+pub fn run_intcode(intcode: &[Word], inputs: &[Word]) -> Computer {
     let mut computer = Computer::new(intcode, inputs);
     computer.run().unwrap();
     computer
 }
 
-pub fn load_intcode(path: &str) -> Vec<i64> {
-    fs::read_to_string(path)
-        .unwrap()
-        .trim()
-        .split(',')
-        .map(|x| x.parse().unwrap())
-        .collect()
+/// `try_load_intcode`, panicking with the path and the error on failure.
+pub fn load_intcode(path: &str) -> Vec<Word> {
+    try_load_intcode(path).unwrap_or_else(|e| panic!("{}: {}", path, e))
 }
 
 #[cfg(test)]
@@ -298,25 +1024,203 @@ mod tests {
 
     #[test]
     fn test_day09() {
-        let intcode = &[
-            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
-        ];
-        let computer = run_intcode(intcode, &[]);
-        assert_eq!(computer.outputs(), intcode);
+        for &arithmetic in &[Arithmetic::Checked, Arithmetic::Wrapping] {
+            let run = |intcode: &[Word], inputs: &[Word]| {
+                let mut computer = Computer::new(intcode, inputs);
+                computer.set_arithmetic(arithmetic);
+                computer.run().unwrap();
+                computer
+            };
+
+            let intcode = &[
+                109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+            ];
+            assert_eq!(run(intcode, &[]).outputs(), intcode);
+
+            let intcode = &[1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
+            let last_output = run(intcode, &[]).last_output().unwrap();
+            assert_eq!(last_output.to_string().len(), 16);
+
+            let intcode = &[104, 1_125_899_906_842_624, 99];
+            let last_output = run(intcode, &[]).last_output().unwrap();
+            assert_eq!(last_output, intcode[1]);
+
+            let intcode = load_intcode("../day09/input/input.txt");
+            assert_eq!(run(&intcode, &[1]).last_output(), Some(2_316_632_620));
+            assert_eq!(run(&intcode, &[2]).last_output(), Some(78869));
+        }
+    }
+
+    #[test]
+    fn test_overflow() {
+        let intcode = &[1101, Word::MAX, 1, 7, 4, 7, 99, 0];
+        let mut computer = Computer::new(intcode, &[]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::Overflow {
+                ip: 0,
+                instruction: 1101
+            })
+        );
+        assert_eq!(computer.memory()[7], 0);
+
+        computer.set_arithmetic(Arithmetic::Wrapping);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.outputs(), &[Word::MIN]);
+
+        let mut computer = Computer::new(&[109, Word::MAX, 204, 1, 99], &[]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::Overflow {
+                ip: 2,
+                instruction: 204
+            })
+        );
+        assert_eq!(computer.arithmetic(), Arithmetic::Checked);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut computer = Computer::new(&[1, 0, 0, 0, 42], &[]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::IllegalOpcode {
+                ip: 4,
+                instruction: 42
+            })
+        );
 
-        let intcode = &[1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0];
-        let last_output = run_intcode(intcode, &[]).last_output().unwrap();
-        assert_eq!(last_output.to_string().len(), 16);
+        let mut computer = Computer::new(&[301, 0, 0, 0, 99], &[]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::IllegalMode {
+                ip: 0,
+                instruction: 301,
+                mode: 3
+            })
+        );
+
+        let mut computer = Computer::new(&[1, -1, 0, 0, 99], &[]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::NegativeAddress {
+                ip: 0,
+                instruction: 1,
+                address: -1
+            })
+        );
+
+        let mut computer = Computer::new(&[11101, 1, 1, 0, 99], &[]);
+        assert_eq!(
+            computer.run(),
+            Err(IntcodeError::ImmediateWrite {
+                ip: 0,
+                instruction: 11101
+            })
+        );
+
+        let mut computer = Computer::new(&[1105, 1, 100], &[]);
+        assert_eq!(computer.run(), Err(IntcodeError::IpOutOfRange { ip: 100 }));
+    }
+
+    #[test]
+    fn test_needs_input() {
+        // Reads twice before writing the sum, which used to break day 7's feedback loop.
+        let mut computer = Computer::new(&[3, 13, 3, 14, 1, 13, 14, 15, 104, 5, 4, 15, 99], &[]);
+        assert_eq!(computer.step(), Ok(Status::NeedsInput));
+        computer.push_input(3);
+        assert_eq!(computer.run(), Ok(Status::NeedsInput));
+        computer.push_input(4);
+        assert_eq!(computer.step(), Ok(Status::Output(5)));
+        assert_eq!(computer.step(), Ok(Status::Output(7)));
+        assert_eq!(computer.step(), Ok(Status::Halted));
+        assert!(computer.is_halted());
+        assert_eq!(computer.run(), Ok(Status::Halted));
+    }
 
-        let intcode = &[104, 1_125_899_906_842_624, 99];
-        let last_output = run_intcode(intcode, &[]).last_output().unwrap();
-        assert_eq!(last_output, intcode[1]);
+    #[test]
+    fn test_run_after_halt() {
+        let mut computer = Computer::new(&[104, 1, 99], &[]);
+        computer.set_limits(Limits::none().instructions(2));
+        let mut profiler = Profiler::new();
+        assert_eq!(computer.run_with(&mut profiler), Ok(Status::Halted));
+        assert_eq!(computer.run_with(&mut profiler), Ok(Status::Halted));
+        assert_eq!(computer.step(), Ok(Status::Halted));
+        assert_eq!(computer.counters().instructions, 2);
+        assert_eq!(profiler.opcode(Op::Halt), 1);
+        assert_eq!(computer.outputs(), &[1]);
+    }
 
-        let intcode = load_intcode("../day09/input/input.txt");
+    #[test]
+    fn test_decode_cache_invalidation() {
+        let program = assemble(
+            "       out [x]
+             code:  add [x], #2, [x]
+                    add #1002, #0, [code]
+                    out [x]
+                    add [n], #-1, [n]
+                    jt [n], #code
+                    hlt
+             x:     .data 3
+             n:     .data 2",
+        )
+        .unwrap();
+        for &enabled in &[true, false] {
+            let mut computer = Computer::new(&program, &[]);
+            computer.set_decode_cache(enabled);
+            assert_eq!(computer.run(), Ok(Status::Halted));
+            assert_eq!(computer.outputs(), &[3, 5, 10]);
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let program = assemble("loop: jt #1, #loop").unwrap();
+        let mut computer = Computer::new(&program, &[]);
+        computer.set_limits(Limits::none().instructions(100));
         assert_eq!(
-            run_intcode(&intcode, &[1]).last_output(),
-            Some(2_316_632_620)
+            computer.run(),
+            Err(IntcodeError::LimitExceeded {
+                ip: 0,
+                instruction: 1105,
+                limit: Limit::Instructions,
+                counters: Counters {
+                    instructions: 100,
+                    memory: 3,
+                    outputs: 0
+                }
+            })
         );
-        assert_eq!(run_intcode(&intcode, &[2]).last_output(), Some(78869));
+        computer.set_limits(Limits::none().instructions(250));
+        assert!(computer.run().is_err());
+        assert_eq!(computer.counters().instructions, 250);
+
+        let program = assemble("add #1, #2, [10000]\nout [10000]\nout #5\nhlt").unwrap();
+        let mut computer = Computer::new(&program, &[]);
+        computer.set_limits(Limits::none().memory(1000).outputs(1));
+        match computer.run() {
+            Err(IntcodeError::LimitExceeded {
+                limit: Limit::Memory,
+                counters,
+                ..
+            }) => assert_eq!(counters.memory, program.len()),
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(computer.ip(), 0);
+
+        computer.set_limits(Limits::none().memory(10001).outputs(1));
+        match computer.run() {
+            Err(IntcodeError::LimitExceeded {
+                ip: 6,
+                limit: Limit::Outputs,
+                ..
+            }) => (),
+            result => panic!("unexpected {:?}", result),
+        }
+        assert_eq!(computer.outputs(), &[3]);
+
+        computer.set_limits(Limits::default());
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.outputs(), &[3, 5]);
     }
 }
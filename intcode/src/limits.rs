@@ -0,0 +1,76 @@
+use std::fmt;
+
+/// Caps on what a `Computer` may consume. `None` means unlimited, which is the default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub instructions: Option<u64>,
+    /// Maximum number of allocated memory cells, as reported by `Memory::footprint`.
+    pub memory: Option<usize>,
+    pub outputs: Option<usize>,
+}
+
+impl Limits {
+    pub const fn none() -> Self {
+        Self {
+            instructions: None,
+            memory: None,
+            outputs: None,
+        }
+    }
+
+    pub const fn instructions(self, max: u64) -> Self {
+        Self {
+            instructions: Some(max),
+            ..self
+        }
+    }
+
+    pub const fn memory(self, max: usize) -> Self {
+        Self {
+            memory: Some(max),
+            ..self
+        }
+    }
+
+    pub const fn outputs(self, max: usize) -> Self {
+        Self {
+            outputs: Some(max),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Instructions,
+    Memory,
+    Outputs,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Instructions => write!(f, "instruction"),
+            Self::Memory => write!(f, "memory"),
+            Self::Outputs => write!(f, "output"),
+        }
+    }
+}
+
+/// What a `Computer` has consumed so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub instructions: u64,
+    pub memory: usize,
+    pub outputs: usize,
+}
+
+impl fmt::Display for Counters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} instructions, {} memory cells, {} outputs",
+            self.instructions, self.memory, self.outputs
+        )
+    }
+}
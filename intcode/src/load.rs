@@ -0,0 +1,285 @@
+//! Loading programs from text or a compact binary encoding.
+//!
+//! Text programs are integers separated by commas and/or whitespace. A trailing comma is
+//! fine, and `#` starts a comment that runs to the end of the line:
+//!
+//! ```text
+//! # day 2
+//! 1,0,0,3,
+//! 99
+//! ```
+//!
+//! Binary programs start with `BINARY_MAGIC`, followed by the number of words and then every
+//! word, all as LEB128 varints with words zigzag encoded so small negative values stay short.
+//! Readers accept either format.
+
+use crate::Word;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+
+/// Can't be the start of a text program.
+pub const BINARY_MAGIC: &[u8; 4] = b"\0icb";
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(io::Error),
+    /// Something other than an integer, at a byte offset into the text.
+    Token {
+        offset: usize,
+        token: String,
+    },
+    /// A malformed binary program, at a byte offset into it.
+    Binary {
+        offset: usize,
+        message: &'static str,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Token { offset, token } => {
+                write!(f, "invalid integer {:?} at byte {}", token, offset)
+            }
+            Self::Binary { offset, message } => {
+                write!(f, "invalid binary program at byte {}: {}", offset, message)
+            }
+        }
+    }
+}
+
+impl Error for LoadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for LoadError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// Parses a text program.
+pub fn parse_intcode(text: &str) -> Result<Vec<Word>, LoadError> {
+    let bytes = text.as_bytes();
+    let mut program = Vec::new();
+    let mut separated = true;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b',' if !separated => {
+                separated = true;
+                i += 1;
+            }
+            b',' => {
+                return Err(LoadError::Token {
+                    offset: i,
+                    token: ",".to_string(),
+                })
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            _ => {
+                let start = i;
+                while i < bytes.len()
+                    && !matches!(bytes[i], b',' | b'#')
+                    && !bytes[i].is_ascii_whitespace()
+                {
+                    i += 1;
+                }
+                let token = &text[start..i];
+                let value = token.parse().map_err(|_| LoadError::Token {
+                    offset: start,
+                    token: token.to_string(),
+                })?;
+                program.push(value);
+                separated = false;
+            }
+        }
+    }
+    Ok(program)
+}
+
+/// Reads a text or binary program to its end.
+pub fn read_intcode<R: Read>(mut reader: R) -> Result<Vec<Word>, LoadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    if bytes.starts_with(BINARY_MAGIC) {
+        return decode_binary(&bytes);
+    }
+    let text = String::from_utf8(bytes).map_err(|e| {
+        let offset = e.utf8_error().valid_up_to();
+        LoadError::Token {
+            offset,
+            token: String::from_utf8_lossy(&e.as_bytes()[offset..])
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .next()
+                .unwrap_or("")
+                .to_string(),
+        }
+    })?;
+    parse_intcode(&text)
+}
+
+/// Loads a text or binary program from a file, or from stdin if `path` is `-`.
+pub fn try_load_intcode(path: &str) -> Result<Vec<Word>, LoadError> {
+    if path == "-" {
+        read_intcode(io::stdin().lock())
+    } else {
+        read_intcode(fs::File::open(path)?)
+    }
+}
+
+pub fn encode_binary(program: &[Word]) -> Vec<u8> {
+    let mut bytes = BINARY_MAGIC.to_vec();
+    write_varint(&mut bytes, program.len() as u128);
+    for &word in program {
+        #[allow(clippy::useless_conversion)] // Word is i128 with the i128 feature
+        let word = i128::from(word);
+        write_varint(&mut bytes, ((word << 1) ^ (word >> 127)) as u128);
+    }
+    bytes
+}
+
+pub fn write_binary<W: Write>(program: &[Word], mut writer: W) -> io::Result<()> {
+    writer.write_all(&encode_binary(program))
+}
+
+pub fn save_binary(program: &[Word], path: &str) -> io::Result<()> {
+    fs::write(path, encode_binary(program))
+}
+
+pub fn decode_binary(bytes: &[u8]) -> Result<Vec<Word>, LoadError> {
+    if !bytes.starts_with(BINARY_MAGIC) {
+        return Err(LoadError::Binary {
+            offset: 0,
+            message: "missing magic number",
+        });
+    }
+    let mut offset = BINARY_MAGIC.len();
+    let len = read_varint(bytes, &mut offset)?;
+    // Every word takes at least a byte, which also keeps a corrupt length from allocating.
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|&len| len <= bytes.len() - offset)
+        .ok_or(LoadError::Binary {
+            offset: BINARY_MAGIC.len(),
+            message: "length past the end",
+        })?;
+
+    let mut program = Vec::with_capacity(len);
+    for _ in 0..len {
+        let start = offset;
+        let zigzag = read_varint(bytes, &mut offset)?;
+        let value = (zigzag >> 1) as i128 ^ -((zigzag & 1) as i128);
+        program.push(Word::try_from(value).map_err(|_| LoadError::Binary {
+            offset: start,
+            message: "word out of range",
+        })?);
+    }
+    if offset != bytes.len() {
+        return Err(LoadError::Binary {
+            offset,
+            message: "trailing bytes",
+        });
+    }
+    Ok(program)
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u128) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], offset: &mut usize) -> Result<u128, LoadError> {
+    let start = *offset;
+    let mut value = 0u128;
+    for shift in (0..128).step_by(7) {
+        let byte = *bytes.get(*offset).ok_or(LoadError::Binary {
+            offset: *offset,
+            message: "unexpected end",
+        })?;
+        *offset += 1;
+        value |= u128::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(LoadError::Binary {
+        offset: start,
+        message: "varint too long",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = "# day 2\n1,0, -3,\n99  # halt\n\n1 2\t3,\n";
+        assert_eq!(parse_intcode(text).unwrap(), vec![1, 0, -3, 99, 1, 2, 3]);
+        assert_eq!(parse_intcode("").unwrap(), vec![]);
+
+        let error = parse_intcode("1,0,x3,99").unwrap_err();
+        assert_eq!(error.to_string(), "invalid integer \"x3\" at byte 4");
+        let error = parse_intcode("1,\n,2").unwrap_err();
+        assert_eq!(error.to_string(), "invalid integer \",\" at byte 3");
+        let error = parse_intcode("1,2#\n3-").unwrap_err();
+        assert_eq!(error.to_string(), "invalid integer \"3-\" at byte 5");
+    }
+
+    #[test]
+    fn test_binary() {
+        let program = vec![1, -1, 0, 63, -64, 64, 1_125_899_906_842_624, Word::MIN, 99];
+        let bytes = encode_binary(&program);
+        assert_eq!(&bytes[4..9], &[9, 2, 1, 0, 126]);
+        assert_eq!(decode_binary(&bytes).unwrap(), program);
+        assert_eq!(read_intcode(&bytes[..]).unwrap(), program);
+
+        let error = decode_binary(&bytes[..bytes.len() - 1]).unwrap_err();
+        assert!(error.to_string().ends_with("unexpected end"));
+        let error = decode_binary(&bytes[..8]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid binary program at byte 4: length past the end"
+        );
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        let error = decode_binary(&trailing).unwrap_err();
+        assert!(error.to_string().ends_with("trailing bytes"));
+        let mut long = BINARY_MAGIC.to_vec();
+        long.extend(&[1, 0xff]);
+        let error = decode_binary(&long).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "invalid binary program at byte 6: unexpected end"
+        );
+    }
+
+    #[test]
+    fn test_read() {
+        assert_eq!(read_intcode(&b"1,2\n"[..]).unwrap(), vec![1, 2]);
+        let error = read_intcode(&b"1,\xff\xfe,2"[..]).unwrap_err();
+        assert!(error.to_string().ends_with("at byte 2"));
+        assert!(matches!(
+            try_load_intcode("no/such/file"),
+            Err(LoadError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::Word;
+use std::collections::HashMap;
+
+/// Storage behind a `Computer`. Cells that were never written read as 0.
+pub trait Memory {
+    fn read(&self, address: usize) -> Word;
+
+    fn write(&mut self, address: usize, value: Word);
+
+    /// Whether `address` is backed by storage, i.e. part of the loaded program or written to.
+    fn contains(&self, address: usize) -> bool;
+
+    /// Number of cells currently allocated.
+    fn footprint(&self) -> usize;
+
+    /// Number of cells writing `address` would allocate.
+    fn growth(&self, address: usize) -> usize;
+
+    /// Allocated cells as `(start address, words)` runs, in address order.
+    fn regions(&self) -> Vec<(usize, &[Word])>;
+
+    fn load(program: &[Word]) -> Self
+    where
+        Self: Default,
+    {
+        let mut memory = Self::default();
+        for (address, &value) in program.iter().enumerate() {
+            memory.write(address, value);
+        }
+        memory
+    }
+}
+
+/// Plain vector, grown to cover the highest address written. This is the default and the
+/// fastest backend, but a single write far out allocates everything below it.
+impl Memory for Vec<Word> {
+    #[inline]
+    fn read(&self, address: usize) -> Word {
+        self.get(address).cloned().unwrap_or(0)
+    }
+
+    #[inline]
+    fn write(&mut self, address: usize, value: Word) {
+        if self.len() < address + 1 {
+            self.resize(address + 1, 0);
+        }
+        self[address] = value;
+    }
+
+    #[inline]
+    fn contains(&self, address: usize) -> bool {
+        address < self.len()
+    }
+
+    #[inline]
+    fn footprint(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn growth(&self, address: usize) -> usize {
+        (address + 1).saturating_sub(self.len())
+    }
+
+    fn regions(&self) -> Vec<(usize, &[Word])> {
+        vec![(0, &self[..])]
+    }
+
+    fn load(program: &[Word]) -> Self {
+        program.to_vec()
+    }
+}
+
+pub const PAGE_SIZE: usize = 4096;
+
+/// Memory split into `PAGE_SIZE` word pages that are allocated on first write, for programs
+/// that scatter writes over a huge address space.
+#[derive(Debug, Clone, Default)]
+pub struct PagedMemory {
+    pages: HashMap<usize, Box<[Word]>>,
+}
+
+impl Memory for PagedMemory {
+    fn read(&self, address: usize) -> Word {
+        self.pages
+            .get(&(address / PAGE_SIZE))
+            .map_or(0, |page| page[address % PAGE_SIZE])
+    }
+
+    fn write(&mut self, address: usize, value: Word) {
+        let page = address / PAGE_SIZE;
+        if value == 0 && !self.pages.contains_key(&page) {
+            return;
+        }
+        self.pages
+            .entry(page)
+            .or_insert_with(|| vec![0; PAGE_SIZE].into_boxed_slice())[address % PAGE_SIZE] = value;
+    }
+
+    fn contains(&self, address: usize) -> bool {
+        self.pages.contains_key(&(address / PAGE_SIZE))
+    }
+
+    fn footprint(&self) -> usize {
+        self.pages.len() * PAGE_SIZE
+    }
+
+    fn growth(&self, address: usize) -> usize {
+        if self.contains(address) {
+            0
+        } else {
+            PAGE_SIZE
+        }
+    }
+
+    fn regions(&self) -> Vec<(usize, &[Word])> {
+        let mut regions: Vec<_> = self
+            .pages
+            .iter()
+            .map(|(&page, words)| (page * PAGE_SIZE, &words[..]))
+            .collect();
+        regions.sort_by_key(|&(start, _)| start);
+        regions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Computer, Status};
+
+    #[test]
+    fn test_paged_memory() {
+        let program = assemble(
+            "add #1, #2, [1000000000000]
+             out [1000000000000]
+             hlt",
+        )
+        .unwrap();
+        let mut computer = Computer::with_memory(PagedMemory::load(&program), &[]);
+        assert_eq!(computer.run(), Ok(Status::Halted));
+        assert_eq!(computer.outputs(), &[3]);
+        assert_eq!(computer.peek(1_000_000_000_000), 3);
+        assert_eq!(computer.peek(999_999_999_999), 0);
+
+        let regions = computer.memory_regions();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].0, 0);
+        assert_eq!(&regions[0].1[..program.len()], &program[..]);
+        assert_eq!(regions[1].0, 1_000_000_000_000 / PAGE_SIZE * PAGE_SIZE);
+        assert_eq!(computer.memory_footprint(), 2 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_zero_writes_do_not_allocate() {
+        let mut memory = PagedMemory::default();
+        memory.write(1 << 40, 0);
+        assert_eq!(memory.footprint(), 0);
+        assert!(!memory.contains(1 << 40));
+    }
+}
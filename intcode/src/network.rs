@@ -0,0 +1,320 @@
+//! Networks of computers exchanging `(destination, x, y)` packets.
+//!
+//! Every node runs the same program and is booted with its address as its first input. The
+//! network runs in rounds: each node in address order gets its queued packets as `x, y` input
+//! pairs, or a single `-1` if nothing is queued, and runs until it waits for input again.
+//! Output triples become packets and are queued for their destination immediately. Packets
+//! for the monitor address go to a `Monitor` instead, which is also asked what to do whenever
+//! a whole round passes with every queue empty and nothing sent.
+
+use crate::{Computer, IntcodeError, Word};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+pub const DEFAULT_MONITOR_ADDRESS: Word = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Packet {
+    pub dest: Word,
+    pub x: Word,
+    pub y: Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+pub trait Monitor {
+    fn receive(&mut self, packet: Packet) -> Control;
+
+    /// Called when the network is idle. Returning `None` stops the network, since nothing
+    /// would ever happen again.
+    fn idle(&mut self) -> Option<Packet>;
+}
+
+/// Remembers the last packet it received and sends it to address 0 whenever the network is
+/// idle. Stops once it sends the same `y` twice in a row.
+#[derive(Debug, Default)]
+pub struct Nat {
+    pub last: Option<Packet>,
+    pub sent: Vec<Packet>,
+}
+
+impl Monitor for Nat {
+    fn receive(&mut self, packet: Packet) -> Control {
+        self.last = Some(packet);
+        Control::Continue
+    }
+
+    fn idle(&mut self) -> Option<Packet> {
+        let packet = Packet {
+            dest: 0,
+            ..self.last?
+        };
+        if self.sent.last().map(|p| p.y) == Some(packet.y) {
+            return None;
+        }
+        self.sent.push(packet);
+        Some(packet)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The monitor returned `Control::Stop`.
+    Monitor,
+    /// The network went idle and the monitor had nothing to inject.
+    Idle,
+    Halted,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeError {
+    pub address: usize,
+    pub error: IntcodeError,
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node {}: {}", self.address, self.error)
+    }
+}
+
+impl Error for NodeError {}
+
+struct Node {
+    computer: Computer,
+    queue: VecDeque<Packet>,
+    partial: Vec<Word>,
+}
+
+pub struct Network {
+    nodes: Vec<Node>,
+    monitor_address: Word,
+    undeliverable: Vec<Packet>,
+    rounds: usize,
+}
+
+impl Network {
+    pub fn new(program: &[Word], size: usize) -> Self {
+        Self {
+            nodes: (0..size)
+                .map(|address| Node {
+                    computer: Computer::new(program, &[address as Word]),
+                    queue: VecDeque::new(),
+                    partial: Vec::new(),
+                })
+                .collect(),
+            monitor_address: DEFAULT_MONITOR_ADDRESS,
+            undeliverable: Vec::new(),
+            rounds: 0,
+        }
+    }
+
+    pub fn with_monitor_address(self, monitor_address: Word) -> Self {
+        Self {
+            monitor_address,
+            ..self
+        }
+    }
+
+    pub fn computer(&self, address: usize) -> &Computer {
+        &self.nodes[address].computer
+    }
+
+    /// Packets addressed to neither a node nor the monitor.
+    pub fn undeliverable(&self) -> &[Packet] {
+        &self.undeliverable
+    }
+
+    pub const fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    /// Queues `packet` for its destination node, without going through the monitor.
+    pub fn send(&mut self, packet: Packet) {
+        match usize::try_from(packet.dest)
+            .ok()
+            .and_then(|address| self.nodes.get_mut(address))
+        {
+            Some(node) => node.queue.push_back(packet),
+            _ => self.undeliverable.push(packet),
+        }
+    }
+
+    pub fn run<M: Monitor>(&mut self, monitor: &mut M) -> Result<Stop, NodeError> {
+        loop {
+            if let Some(stop) = self.round(monitor)? {
+                return Ok(stop);
+            }
+        }
+    }
+
+    /// Runs a single round. Returns `Some` if the network should stop.
+    pub fn round<M: Monitor>(&mut self, monitor: &mut M) -> Result<Option<Stop>, NodeError> {
+        self.rounds += 1;
+        let mut idle = true;
+
+        for address in 0..self.nodes.len() {
+            let node = &mut self.nodes[address];
+            if node.computer.is_halted() {
+                continue;
+            }
+
+            if node.queue.is_empty() {
+                node.computer.push_input(-1);
+            } else {
+                idle = false;
+                for packet in node.queue.drain(..) {
+                    node.computer.push_input(packet.x);
+                    node.computer.push_input(packet.y);
+                }
+            }
+
+            node.computer
+                .run()
+                .map_err(|error| NodeError { address, error })?;
+            node.partial.append(node.computer.output_mut());
+
+            let complete = node.partial.len() / 3 * 3;
+            let packets: Vec<Packet> = node
+                .partial
+                .drain(..complete)
+                .collect::<Vec<_>>()
+                .chunks(3)
+                .map(|c| Packet {
+                    dest: c[0],
+                    x: c[1],
+                    y: c[2],
+                })
+                .collect();
+
+            for packet in packets {
+                idle = false;
+                if packet.dest == self.monitor_address {
+                    if monitor.receive(packet) == Control::Stop {
+                        return Ok(Some(Stop::Monitor));
+                    }
+                } else {
+                    self.send(packet);
+                }
+            }
+        }
+
+        if self.nodes.iter().all(|node| node.computer.is_halted()) {
+            return Ok(Some(Stop::Halted));
+        }
+        if idle && self.nodes.iter().all(|node| node.queue.is_empty()) {
+            match monitor.idle() {
+                Some(packet) => self.send(packet),
+                None => return Ok(Some(Stop::Idle)),
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    // Node 0 starts by sending (1, 5, 7). Every node answers a packet (x, y) by sending
+    // (255, x + address, y) to the monitor.
+    const NODE: &str = "
+                in [addr]
+                jt [addr], #recv
+                out #1
+                out #5
+                out #7
+        recv:   in [x]
+                eq [x], #-1, [t]
+                jt [t], #recv
+                in [y]
+                add [x], [addr], [x]
+                out #255
+                out [x]
+                out [y]
+                jt #1, #recv
+        addr:   .data 0
+        x:      .data 0
+        y:      .data 0
+        t:      .data 0";
+
+    #[derive(Default)]
+    struct Recorder {
+        received: Vec<Packet>,
+        idles: usize,
+    }
+
+    impl Monitor for Recorder {
+        fn receive(&mut self, packet: Packet) -> Control {
+            self.received.push(packet);
+            if self.received.len() == 2 {
+                Control::Stop
+            } else {
+                Control::Continue
+            }
+        }
+
+        fn idle(&mut self) -> Option<Packet> {
+            self.idles += 1;
+            Some(Packet {
+                dest: 2,
+                x: 100,
+                y: 200,
+            })
+        }
+    }
+
+    #[test]
+    fn test_routing() {
+        let mut network = Network::new(&assemble(NODE).unwrap(), 3);
+        let mut recorder = Recorder::default();
+
+        assert_eq!(network.run(&mut recorder), Ok(Stop::Monitor));
+        assert_eq!(
+            recorder.received,
+            vec![
+                Packet {
+                    dest: 255,
+                    x: 6,
+                    y: 7
+                },
+                Packet {
+                    dest: 255,
+                    x: 102,
+                    y: 200
+                }
+            ]
+        );
+        assert_eq!(recorder.idles, 1);
+        assert_eq!(network.rounds(), 3);
+    }
+
+    #[test]
+    fn test_nat() {
+        let mut network = Network::new(&assemble(NODE).unwrap(), 2);
+        let mut nat = Nat::default();
+
+        assert_eq!(network.run(&mut nat), Ok(Stop::Idle));
+        assert_eq!(nat.last.map(|p| (p.x, p.y)), Some((6, 7)));
+        assert_eq!(nat.sent.len(), 1);
+
+        // Past the end, negative, and wrapping around to node 1 if truncated to usize.
+        let dests: Vec<Word> = [9, -1]
+            .iter()
+            .copied()
+            .chain(Word::try_from(u128::from(u64::MAX) + 2))
+            .collect();
+        for &dest in &dests {
+            network.send(Packet { dest, x: 0, y: 0 });
+        }
+        assert_eq!(network.undeliverable().len(), dests.len());
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{Instruction, Op, Word};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryWrite {
+    pub address: Word,
+    pub old: Word,
+    pub value: Word,
+}
+
+/// Everything one instruction did, as seen by `Observer::after`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Execution {
+    pub ip: Word,
+    pub word: Word,
+    pub instruction: Instruction,
+    /// Resolved parameters: the value for parameters that are read, the target address for
+    /// the one that is written. Only the first `op.arity()` entries are meaningful.
+    pub operands: [Word; 3],
+    pub next_ip: Word,
+    /// For extended instructions with several write parameters, the first of their writes.
+    pub write: Option<MemoryWrite>,
+    /// Relative base before and after an `arb`.
+    pub base: Option<(Word, Word)>,
+    pub input: Option<Word>,
+    pub output: Option<Word>,
+}
+
+impl Execution {
+    pub const fn op(&self) -> Op {
+        self.instruction.op
+    }
+
+    pub fn operands(&self) -> &[Word] {
+        &self.operands[..self.op().arity()]
+    }
+}
+
+impl fmt::Display for Execution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>5}  {}", self.ip, self.op().mnemonic())?;
+        for (i, operand) in self.operands().iter().enumerate() {
+            if self.op().write_param() != Some(i) {
+                write!(f, "{}{}", if i == 0 { " " } else { ", " }, operand)?;
+            }
+        }
+        if let Some(write) = self.write {
+            write!(f, " -> [{}] = {}", write.address, write.value)?;
+        }
+        if let Some((_, base)) = self.base {
+            write!(f, " -> rb = {}", base)?;
+        }
+        if let Some(output) = self.output {
+            write!(f, " -> out {}", output)?;
+        }
+        if self.next_ip != self.ip + self.op().arity() as Word + 1 && self.op() != Op::Halt {
+            write!(f, " -> jump {}", self.next_ip)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hooks `Computer` calls around every instruction it executes. A read that has to wait for
+/// input isn't executed and isn't reported.
+pub trait Observer {
+    /// Called once the instruction is decoded and its operands are resolved (see
+    /// `Execution::operands`), before it changes anything.
+    fn before(&mut self, _ip: Word, _instruction: &Instruction, _operands: &[Word]) {}
+
+    fn after(&mut self, _execution: &Execution) {}
+}
+
+impl Observer for () {}
+
+impl<O: Observer + ?Sized> Observer for &mut O {
+    fn before(&mut self, ip: Word, instruction: &Instruction, operands: &[Word]) {
+        (**self).before(ip, instruction, operands);
+    }
+
+    fn after(&mut self, execution: &Execution) {
+        (**self).after(execution);
+    }
+}
+
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn before(&mut self, ip: Word, instruction: &Instruction, operands: &[Word]) {
+        self.0.before(ip, instruction, operands);
+        self.1.before(ip, instruction, operands);
+    }
+
+    fn after(&mut self, execution: &Execution) {
+        self.0.after(execution);
+        self.1.after(execution);
+    }
+}
+
+/// Writes one JSON object per executed instruction. The first write error stops the trace
+/// and is returned from `finish`.
+pub struct JsonTracer<W: Write> {
+    out: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JsonTracer<W> {
+    pub const fn new(out: W) -> Self {
+        Self { out, error: None }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.out.flush().map(|_| self.out),
+        }
+    }
+
+    fn write(&mut self, e: &Execution) -> io::Result<()> {
+        let operands = e
+            .operands()
+            .iter()
+            .map(|o| o.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(
+            self.out,
+            r#"{{"ip":{},"word":{},"op":"{}","operands":[{}],"next_ip":{}"#,
+            e.ip,
+            e.word,
+            e.op().mnemonic(),
+            operands,
+            e.next_ip
+        )?;
+        if let Some(w) = e.write {
+            write!(
+                self.out,
+                r#","write":{{"address":{},"old":{},"value":{}}}"#,
+                w.address, w.old, w.value
+            )?;
+        }
+        if let Some((old, new)) = e.base {
+            write!(self.out, r#","base":{{"old":{},"new":{}}}"#, old, new)?;
+        }
+        if let Some(input) = e.input {
+            write!(self.out, r#","input":{}"#, input)?;
+        }
+        if let Some(output) = e.output {
+            write!(self.out, r#","output":{}"#, output)?;
+        }
+        writeln!(self.out, "}}")
+    }
+}
+
+impl<W: Write> Observer for JsonTracer<W> {
+    fn after(&mut self, execution: &Execution) {
+        if self.error.is_none() {
+            if let Err(e) = self.write(execution) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+/// Keeps the last `capacity` executed instructions for post-mortem dumps.
+pub struct RingTracer {
+    capacity: usize,
+    executions: VecDeque<Execution>,
+}
+
+impl RingTracer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            executions: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Oldest first.
+    pub fn executions(&self) -> impl Iterator<Item = &Execution> {
+        self.executions.iter()
+    }
+
+    pub fn last(&self) -> Option<&Execution> {
+        self.executions.back()
+    }
+
+    pub fn dump(&self) -> String {
+        self.executions.iter().map(|e| format!("{}\n", e)).collect()
+    }
+}
+
+impl Observer for RingTracer {
+    fn after(&mut self, execution: &Execution) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.executions.len() == self.capacity {
+            self.executions.pop_front();
+        }
+        self.executions.push_back(*execution);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Computer, Status};
+
+    #[test]
+    fn test_json_tracer() {
+        let program = assemble("in [0]\narb #-2\nout rb+2\nhlt").unwrap();
+        let mut tracer = JsonTracer::new(Vec::new());
+        let mut computer = Computer::new(&program, &[5]);
+        assert_eq!(computer.run_with(&mut tracer), Ok(Status::Halted));
+
+        let trace = String::from_utf8(tracer.finish().unwrap()).unwrap();
+        assert_eq!(
+            trace,
+            r#"{"ip":0,"word":3,"op":"in","operands":[0],"next_ip":2,"write":{"address":0,"old":3,"value":5},"input":5}
+{"ip":2,"word":109,"op":"arb","operands":[-2],"next_ip":4,"base":{"old":0,"new":-2}}
+{"ip":4,"word":204,"op":"out","operands":[5],"next_ip":6,"output":5}
+{"ip":6,"word":99,"op":"hlt","operands":[],"next_ip":6}
+"#
+        );
+    }
+
+    #[test]
+    fn test_ring_tracer() {
+        let program = assemble(
+            "
+            loop:   add [n], #-1, [n]
+                    jt [n], #loop
+                    hlt
+            n:      .data 3",
+        )
+        .unwrap();
+        let mut ring = RingTracer::new(3);
+        let mut before = 0;
+        let mut counter = (&mut ring, Counter(&mut before));
+        Computer::new(&program, &[]).run_with(&mut counter).unwrap();
+
+        assert_eq!(before, 7);
+        assert_eq!(
+            ring.dump(),
+            "    0  add 1, -1 -> [8] = 0
+    4  jt 0, 0
+    7  hlt
+"
+        );
+    }
+
+    struct Counter<'a>(&'a mut usize);
+
+    impl Observer for Counter<'_> {
+        fn before(&mut self, _: Word, _: &Instruction, _: &[Word]) {
+            *self.0 += 1;
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Sets of memory writes applied to a program before it runs.
+//!
+//! Written as `addr=value` entries separated by commas and/or whitespace, like `1=12,2=2` for
+//! day 2's noun and verb.
+
+use crate::{Computer, InputSource, Memory, OutputSink, Word};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Patch {
+    writes: BTreeMap<usize, Word>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    pub offset: usize,
+    pub entry: String,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid patch entry {:?} at byte {}, expected addr=value",
+            self.entry, self.offset
+        )
+    }
+}
+
+impl Error for PatchError {}
+
+impl Patch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Day 2's patch: `noun` at address 1 and `verb` at address 2.
+    pub fn noun_verb(noun: Word, verb: Word) -> Self {
+        Self::new().with(1, noun).with(2, verb)
+    }
+
+    /// The cells where `patched` differs from `original`, treating cells past the end of
+    /// either as zero.
+    pub fn diff(original: &[Word], patched: &[Word]) -> Self {
+        let cell = |memory: &[Word], i: usize| memory.get(i).copied().unwrap_or(0);
+        let writes = (0..original.len().max(patched.len()))
+            .filter(|&i| cell(original, i) != cell(patched, i))
+            .map(|i| (i, cell(patched, i)))
+            .collect();
+        Self { writes }
+    }
+
+    pub fn with(mut self, address: usize, value: Word) -> Self {
+        self.set(address, value);
+        self
+    }
+
+    /// Replaces any earlier write to `address`.
+    pub fn set(&mut self, address: usize, value: Word) {
+        self.writes.insert(address, value);
+    }
+
+    pub fn get(&self, address: usize) -> Option<Word> {
+        self.writes.get(&address).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    /// `(address, value)` pairs by address.
+    pub fn writes(&self) -> impl Iterator<Item = (usize, Word)> + '_ {
+        self.writes.iter().map(|(&a, &v)| (a, v))
+    }
+
+    /// Writes into `program`, growing it with zeros if needed.
+    pub fn apply(&self, program: &mut Vec<Word>) {
+        for (address, value) in self.writes() {
+            if program.len() <= address {
+                program.resize(address + 1, 0);
+            }
+            program[address] = value;
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// One entry per line.
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (address, value) in self.writes() {
+            writeln!(f, "{}={}", address, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Patch {
+    type Err = PatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut patch = Self::new();
+        for entry in s.split(|c: char| c == ',' || c.is_whitespace()) {
+            if entry.is_empty() {
+                continue;
+            }
+            let error = || PatchError {
+                offset: entry.as_ptr() as usize - s.as_ptr() as usize,
+                entry: entry.to_string(),
+            };
+            let mut parts = entry.splitn(2, '=');
+            let address = parts
+                .next()
+                .and_then(|a| a.parse().ok())
+                .ok_or_else(error)?;
+            let value = parts
+                .next()
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(error)?;
+            patch.set(address, value);
+        }
+        Ok(patch)
+    }
+}
+
+impl<M: Memory, I: InputSource, O: OutputSink> Computer<M, I, O> {
+    /// Applies `patch` to memory, typically before running.
+    pub fn patch(&mut self, patch: &Patch) {
+        for (address, value) in patch.writes() {
+            self.memory.write(address, value);
+            self.cache.invalidate(address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format() {
+        let patch: Patch = "1=12, 2=2\n7=-1,".parse().unwrap();
+        assert_eq!(patch, Patch::noun_verb(12, 2).with(7, -1));
+        assert_eq!(patch.to_string(), "1=12\n2=2\n7=-1\n");
+        assert_eq!(patch.to_string().parse(), Ok(patch));
+        assert_eq!("".parse(), Ok(Patch::new()));
+
+        assert_eq!(
+            "1=12,x=2".parse::<Patch>(),
+            Err(PatchError {
+                offset: 5,
+                entry: "x=2".to_string()
+            })
+        );
+        assert_eq!(
+            "1=12 -3=1".parse::<Patch>().unwrap_err().to_string(),
+            "invalid patch entry \"-3=1\" at byte 5, expected addr=value"
+        );
+        assert!("2".parse::<Patch>().is_err());
+    }
+
+    #[test]
+    fn test_apply_and_diff() {
+        let original = vec![1, 0, 0, 3, 99];
+        let patch = Patch::noun_verb(4, 4).with(6, 7);
+        let mut program = original.clone();
+        patch.apply(&mut program);
+        assert_eq!(program, vec![1, 4, 4, 3, 99, 0, 7]);
+        assert_eq!(Patch::diff(&original, &program), patch);
+        assert_eq!(
+            Patch::diff(&program, &original),
+            Patch::noun_verb(0, 0).with(6, 0)
+        );
+
+        let mut computer = Computer::new(&original, &[]);
+        computer.patch(&patch);
+        computer.run().unwrap();
+        assert_eq!(computer.memory(), &[1, 4, 4, 198, 99, 0, 7]);
+        assert_eq!(
+            Patch::diff(&original, computer.memory()),
+            patch.with(3, 198)
+        );
+    }
+}
@@ -0,0 +1,247 @@
+//! Execution statistics for finding hot loops and comparing interpreter changes.
+
+use crate::{
+    Computer, Execution, InputSource, IntcodeError, Memory, Observer, Op, OutputSink, Status, Word,
+};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+/// Counts executed instructions per opcode, per address and per mode combination. As a plain
+/// `Observer` it only counts; `Computer::run_profiled` also records wall time and memory use.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profiler {
+    instructions: u64,
+    opcodes: BTreeMap<Word, u64>,
+    addresses: HashMap<Word, u64>,
+    modes: BTreeMap<Word, u64>,
+    base_adjustments: u64,
+    max_memory: usize,
+    elapsed: Duration,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub const fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    /// Executed instructions per opcode.
+    pub fn opcode(&self, op: Op) -> u64 {
+        self.opcodes.get(&op.code()).copied().unwrap_or(0)
+    }
+
+    /// Instructions executed at `address`.
+    pub fn address(&self, address: Word) -> u64 {
+        self.addresses.get(&address).copied().unwrap_or(0)
+    }
+
+    /// Executed instructions per mode combination, keyed by the instruction word with unused
+    /// mode digits dropped, e.g. `1001` for `add` reading a position and an immediate.
+    pub fn modes(&self) -> &BTreeMap<Word, u64> {
+        &self.modes
+    }
+
+    pub const fn base_adjustments(&self) -> u64 {
+        self.base_adjustments
+    }
+
+    /// Largest `Computer::memory_footprint` seen at the end of a profiled run.
+    pub const fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+
+    /// Total time spent in profiled runs.
+    pub const fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// The `n` most executed addresses, most executed first.
+    pub fn hot_spots(&self, n: usize) -> Vec<(Word, u64)> {
+        let mut addresses: Vec<_> = self.addresses.iter().map(|(&a, &c)| (a, c)).collect();
+        addresses.sort_unstable_by_key(|&(address, count)| (Reverse(count), address));
+        addresses.truncate(n);
+        addresses
+    }
+
+    /// A human readable summary listing the `hot_spots` most executed addresses.
+    pub fn report(&self, hot_spots: usize) -> String {
+        let mut out = String::new();
+        let seconds = self.elapsed.as_secs_f64();
+        writeln!(out, "instructions      {}", self.instructions).unwrap();
+        write!(out, "wall time         {:?}", self.elapsed).unwrap();
+        if seconds > 0.0 {
+            write!(
+                out,
+                " ({:.1}M instructions/s)",
+                self.instructions as f64 / seconds / 1e6
+            )
+            .unwrap();
+        }
+        writeln!(out).unwrap();
+        writeln!(out, "max memory        {} cells", self.max_memory).unwrap();
+        writeln!(out, "base adjustments  {}", self.base_adjustments).unwrap();
+
+        writeln!(out, "\nopcode  {:>12}  {:>6}", "count", "share").unwrap();
+        for (&code, &count) in &self.opcodes {
+            let op = opcode_name(code);
+            writeln!(out, "{:<6}  {:>12}  {}", op, count, self.share(count)).unwrap();
+        }
+
+        writeln!(out, "\nmodes         {:>12}  {:>6}", "count", "share").unwrap();
+        for (&word, &count) in &self.modes {
+            let modes = describe_modes(word);
+            writeln!(out, "{:<12}  {:>12}  {}", modes, count, self.share(count)).unwrap();
+        }
+
+        writeln!(out, "\naddress  {:>12}  {:>6}", "count", "share").unwrap();
+        for (address, count) in self.hot_spots(hot_spots) {
+            writeln!(out, "{:>7}  {:>12}  {}", address, count, self.share(count)).unwrap();
+        }
+        out
+    }
+
+    /// The whole profile as a single JSON object, with every executed address.
+    pub fn to_json(&self) -> String {
+        fn object<K: ToString>(entries: impl Iterator<Item = (K, u64)>) -> String {
+            let entries = entries
+                .map(|(key, count)| format!(r#""{}":{}"#, key.to_string(), count))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(","))
+        }
+
+        let opcodes = self
+            .opcodes
+            .iter()
+            .map(|(&code, &count)| (opcode_name(code), count));
+        let addresses = self.addresses.iter().collect::<BTreeMap<_, _>>();
+        format!(
+            r#"{{"instructions":{},"wall_time_ns":{},"max_memory":{},"base_adjustments":{},"opcodes":{},"modes":{},"addresses":{}}}"#,
+            self.instructions,
+            self.elapsed.as_nanos(),
+            self.max_memory,
+            self.base_adjustments,
+            object(opcodes),
+            object(self.modes.iter().map(|(w, &c)| (w, c))),
+            object(addresses.into_iter().map(|(a, &c)| (a, c)))
+        )
+    }
+
+    fn share(&self, count: u64) -> String {
+        format!(
+            "{:>5.1}%",
+            100.0 * count as f64 / self.instructions.max(1) as f64
+        )
+    }
+}
+
+/// The mnemonic, or `ext` and the code for extended instructions.
+fn opcode_name(code: Word) -> String {
+    match Op::try_from(code) {
+        Ok(op) => op.mnemonic().to_string(),
+        Err(_) => format!("ext{}", code),
+    }
+}
+
+/// `1001` becomes `add p,i,p`.
+fn describe_modes(word: Word) -> String {
+    let op = match Op::try_from(word % 100) {
+        Ok(op) => op,
+        Err(_) => return word.to_string(),
+    };
+    let mut digits = word / 100;
+    let modes = (0..op.arity())
+        .map(|_| {
+            let mode = ["p", "i", "r"][(digits % 10) as usize];
+            digits /= 10;
+            mode
+        })
+        .collect::<Vec<_>>();
+    format!("{} {}", op.mnemonic(), modes.join(","))
+        .trim_end()
+        .to_string()
+}
+
+impl Observer for Profiler {
+    fn after(&mut self, execution: &Execution) {
+        let op = execution.op();
+        let mut word = op.code();
+        let mut scale = 100;
+        for mode in &execution.instruction.modes[..op.arity()] {
+            word += mode.digit() * scale;
+            scale *= 10;
+        }
+
+        self.instructions += 1;
+        *self.opcodes.entry(op.code()).or_insert(0) += 1;
+        *self.addresses.entry(execution.ip).or_insert(0) += 1;
+        *self.modes.entry(word).or_insert(0) += 1;
+        if op == Op::AdjustBase {
+            self.base_adjustments += 1;
+        }
+    }
+}
+
+impl<M: Memory, I: InputSource, O: OutputSink> Computer<M, I, O> {
+    /// `run_with(profiler)`, also adding the wall time of the run and the memory footprint at
+    /// its end to `profiler`.
+    pub fn run_profiled(&mut self, profiler: &mut Profiler) -> Result<Status, IntcodeError> {
+        let start = Instant::now();
+        let result = self.run_with(profiler);
+        profiler.elapsed += start.elapsed();
+        profiler.max_memory = profiler.max_memory.max(self.memory_footprint());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    #[test]
+    fn test_profiler() {
+        let program = assemble(
+            "
+            loop:   add [n], #-1, [n]
+                    arb #1
+                    jt [n], #loop
+                    out rb+0
+                    hlt
+            n:      .data 3",
+        )
+        .unwrap();
+        let mut profiler = Profiler::new();
+        let mut computer = Computer::new(&program, &[]);
+        assert_eq!(computer.run_profiled(&mut profiler), Ok(Status::Halted));
+
+        assert_eq!(profiler.instructions(), 11);
+        assert_eq!(profiler.opcode(Op::Add), 3);
+        assert_eq!(profiler.opcode(Op::Multiply), 0);
+        assert_eq!(profiler.address(0), 3);
+        assert_eq!(profiler.address(9), 1);
+        assert_eq!(profiler.base_adjustments(), 3);
+        assert_eq!(profiler.max_memory(), program.len());
+        assert_eq!(profiler.hot_spots(4), vec![(0, 3), (4, 3), (6, 3), (9, 1)]);
+        assert_eq!(
+            profiler.modes().iter().collect::<Vec<_>>(),
+            vec![(&99, &1), (&109, &3), (&204, &1), (&1001, &3), (&1005, &3)]
+        );
+
+        let report = profiler.report(1);
+        assert!(report.contains("add p,i,p                3   27.3%"));
+        assert!(report.contains("      0             3   27.3%"));
+        assert!(!report.contains("      4             3"));
+
+        let json = profiler.to_json();
+        assert!(json.starts_with(r#"{"instructions":11,"wall_time_ns":"#));
+        assert!(json.ends_with(
+            r#""max_memory":13,"base_adjustments":3,"opcodes":{"add":3,"out":1,"jt":3,"arb":3,"hlt":1},"modes":{"99":1,"109":3,"204":1,"1001":3,"1005":3},"addresses":{"0":3,"4":3,"6":3,"9":1,"11":1}}"#
+        ));
+    }
+}
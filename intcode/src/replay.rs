@@ -0,0 +1,404 @@
+//! Replay logs of the I/O of a session, for turning manual runs into regression tests.
+//!
+//! ```text
+//! intcode-replay 1
+//! program 3f1c2b9a0d8e7f65
+//! in 0 5
+//! out 4 10
+//! ```
+//!
+//! Every input consumed and output produced is one line with the ip of the instruction that
+//! did it. The program is identified by `program_hash`.
+
+use crate::{Computer, Execution, IntcodeError, Limits, Observer, Status, Word};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+pub const REPLAY_VERSION: u32 = 1;
+
+const HEADER: &str = "intcode-replay";
+
+/// 64 bit FNV-1a of the program as comma separated decimals, so the hash doesn't depend on the
+/// width of `Word` or on how the program file was formatted.
+pub fn program_hash(program: &[Word]) -> u64 {
+    let text = program
+        .iter()
+        .map(|w| w.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Input { ip: Word, value: Word },
+    Output { ip: Word, value: Word },
+}
+
+/// Records a session when used as the observer of a run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    pub program: u64,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// The replay was recorded with another program.
+    Program {
+        expected: u64,
+        actual: u64,
+    },
+    /// The `index`th output differs, is missing (`actual` is `None`) or wasn't recorded
+    /// (`expected` is `None`). `ip` is where it was produced, or where the run stopped.
+    Output {
+        index: usize,
+        ip: Word,
+        expected: Option<Word>,
+        actual: Option<Word>,
+    },
+    /// The run stopped at `ip` without reading `count` of the recorded inputs.
+    UnusedInputs {
+        ip: Word,
+        count: usize,
+    },
+    Error(IntcodeError),
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = |v: &Option<Word>| v.map_or("nothing".to_string(), |v| v.to_string());
+        match self {
+            Self::Program { expected, actual } => write!(
+                f,
+                "recorded with program {:016x}, not {:016x}",
+                expected, actual
+            ),
+            Self::Output {
+                index,
+                ip,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "output {} at {}: expected {}, got {}",
+                index,
+                ip,
+                value(expected),
+                value(actual)
+            ),
+            Self::UnusedInputs { ip, count } => {
+                write!(f, "stopped at {} with {} inputs left", ip, count)
+            }
+            Self::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for Divergence {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "replay line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ReplayError {}
+
+impl Replay {
+    pub fn new(program: &[Word]) -> Self {
+        Self {
+            program: program_hash(program),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn inputs(&self) -> Vec<Word> {
+        self.events
+            .iter()
+            .filter_map(|e| match *e {
+                Event::Input { value, .. } => Some(value),
+                Event::Output { .. } => None,
+            })
+            .collect()
+    }
+
+    pub fn outputs(&self) -> Vec<Word> {
+        self.events
+            .iter()
+            .filter_map(|e| match *e {
+                Event::Output { value, .. } => Some(value),
+                Event::Input { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Runs `program` with the recorded inputs until it halts or runs out of them, and
+    /// compares the outputs with the recorded ones. Gives up with a `LimitExceeded` error after
+    /// `max_steps` instructions, in case the run never stops.
+    pub fn check(&self, program: &[Word], max_steps: u64) -> Result<(), Divergence> {
+        let actual = program_hash(program);
+        if actual != self.program {
+            return Err(Divergence::Program {
+                expected: self.program,
+                actual,
+            });
+        }
+
+        let expected = self.outputs();
+        let mut computer = Computer::new(program, &self.inputs());
+        computer.set_limits(Limits::none().instructions(max_steps));
+        let mut index = 0;
+        loop {
+            let ip = computer.ip();
+            match computer.step_instruction_with(&mut ()) {
+                Ok(Some(Status::Output(value))) => {
+                    if expected.get(index) != Some(&value) {
+                        return Err(Divergence::Output {
+                            index,
+                            ip,
+                            expected: expected.get(index).copied(),
+                            actual: Some(value),
+                        });
+                    }
+                    index += 1;
+                }
+                Ok(Some(_)) => break,
+                Ok(None) => (),
+                Err(e) => return Err(Divergence::Error(e)),
+            }
+        }
+
+        if index < expected.len() {
+            Err(Divergence::Output {
+                index,
+                ip: computer.ip(),
+                expected: Some(expected[index]),
+                actual: None,
+            })
+        } else if !computer.inputs().is_empty() {
+            Err(Divergence::UnusedInputs {
+                ip: computer.ip(),
+                count: computer.inputs().len(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Observer for Replay {
+    fn after(&mut self, execution: &Execution) {
+        let ip = execution.ip;
+        if let Some(value) = execution.input {
+            self.events.push(Event::Input { ip, value });
+        }
+        if let Some(value) = execution.output {
+            self.events.push(Event::Output { ip, value });
+        }
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, REPLAY_VERSION)?;
+        writeln!(f, "program {:016x}", self.program)?;
+        for event in &self.events {
+            match event {
+                Event::Input { ip, value } => writeln!(f, "in {} {}", ip, value)?,
+                Event::Output { ip, value } => writeln!(f, "out {} {}", ip, value)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Replay {
+    type Err = ReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        let error = |line, message: String| ReplayError { line, message };
+
+        match lines.next() {
+            Some((_, header)) if header == format!("{} {}", HEADER, REPLAY_VERSION) => (),
+            Some((_, header)) if header.starts_with(HEADER) => {
+                return Err(error(1, format!("unsupported version: {}", header)))
+            }
+            _ => return Err(error(1, "not an intcode replay".to_string())),
+        }
+
+        let mut replay = Self::default();
+        let mut program = None;
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let parts: Vec<_> = line.split_whitespace().collect();
+            let number = |text: &str| {
+                text.parse::<Word>()
+                    .map_err(|_| error(n, format!("invalid number: {:?}", text)))
+            };
+            let event = |parts: &[&str]| match parts {
+                [ip, value] => Ok((number(ip)?, number(value)?)),
+                _ => Err(error(n, "expected an ip and a value".to_string())),
+            };
+
+            match parts[0] {
+                "program" if parts.len() == 2 => {
+                    let hash = u64::from_str_radix(parts[1], 16)
+                        .map_err(|_| error(n, format!("invalid hash: {:?}", parts[1])))?;
+                    program = Some(hash);
+                }
+                "in" => {
+                    let (ip, value) = event(&parts[1..])?;
+                    replay.events.push(Event::Input { ip, value });
+                }
+                "out" => {
+                    let (ip, value) = event(&parts[1..])?;
+                    replay.events.push(Event::Output { ip, value });
+                }
+                key => return Err(error(n, format!("unknown key: {:?}", key))),
+            }
+        }
+
+        replay.program = program.ok_or_else(|| error(1, "missing program hash".to_string()))?;
+        Ok(replay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::DOUBLER;
+    use crate::{assemble, terminal_with, Limit};
+
+    #[test]
+    fn test_record_and_check() {
+        let program = assemble(DOUBLER).unwrap();
+        let mut replay = Replay::new(&program);
+        let mut computer = Computer::new(&program, &[3, 4]);
+        computer.run_with(&mut replay).unwrap();
+        computer.push_input(0);
+        computer.run_with(&mut replay).unwrap();
+        assert_eq!(replay.inputs(), vec![3, 4, 0]);
+        assert_eq!(replay.outputs(), vec![6, 8]);
+
+        let text = replay.to_string();
+        assert!(text.starts_with(&format!(
+            "intcode-replay 1\nprogram {:016x}\nin 0 3\nout 9 6\n",
+            program_hash(&program)
+        )));
+        assert_eq!(text.parse::<Replay>(), Ok(replay.clone()));
+        assert_eq!(replay.check(&program, 1000), Ok(()));
+
+        let mut patched = program.clone();
+        patched[7] = 3;
+        replay.program = program_hash(&patched);
+        assert_eq!(
+            replay.check(&patched, 1000),
+            Err(Divergence::Output {
+                index: 0,
+                ip: 9,
+                expected: Some(6),
+                actual: Some(9)
+            })
+        );
+        replay.program = program_hash(&program);
+        assert_eq!(
+            replay.check(&program[..2], 1000),
+            Err(Divergence::Program {
+                expected: replay.program,
+                actual: program_hash(&program[..2])
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_and_unused() {
+        let program = assemble(DOUBLER).unwrap();
+        let mut replay = Replay::new(&program);
+        replay.events = vec![
+            Event::Input { ip: 0, value: 1 },
+            Event::Output { ip: 9, value: 2 },
+            Event::Output { ip: 9, value: 4 },
+        ];
+        assert_eq!(
+            replay.check(&program, 1000),
+            Err(Divergence::Output {
+                index: 1,
+                ip: 0,
+                expected: Some(4),
+                actual: None
+            })
+        );
+
+        replay.events.pop();
+        replay.events.push(Event::Input { ip: 0, value: 0 });
+        replay.events.push(Event::Input { ip: 0, value: 7 });
+        assert_eq!(
+            replay.check(&program, 1000).unwrap_err().to_string(),
+            "stopped at 14 with 1 inputs left"
+        );
+    }
+
+    #[test]
+    fn test_endless_loop() {
+        let program = assemble("loop: jt #1, #loop").unwrap();
+        assert!(matches!(
+            Replay::new(&program).check(&program, 1000),
+            Err(Divergence::Error(IntcodeError::LimitExceeded {
+                limit: Limit::Instructions,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_terminal_session() {
+        let program = assemble(DOUBLER).unwrap();
+        let mut replay = Replay::new(&program);
+        let mut computer = Computer::new(&program, &[]);
+        let mut output = Vec::new();
+        terminal_with(&mut computer, &b"5\n"[..], &mut output, &mut replay).unwrap();
+        assert_eq!(replay.inputs(), vec![53, 10]);
+        assert_eq!(replay.outputs(), vec![106, 20]);
+        assert_eq!(replay.check(&program, 1000), Ok(()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "intcode-replay 1\nin 0 1\n".parse::<Replay>(),
+            Err(ReplayError {
+                line: 1,
+                message: "missing program hash".to_string()
+            })
+        );
+        assert_eq!(
+            "intcode-replay 1\nprogram 0\nout 4\n"
+                .parse::<Replay>()
+                .unwrap_err()
+                .to_string(),
+            "replay line 3: expected an ip and a value"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! Versioned text snapshots of a `Computer`.
+//!
+//! ```text
+//! intcode-snapshot 1
+//! ip 8
+//! base 0
+//! halted 0
+//! instructions 3
+//! inputs 3
+//! outputs 20
+//! memory 0 3,11,1002,11,10,11,4,11,3,11,99,20
+//! ```
+//!
+//! Every allocated memory region gets its own `memory <start> <words>` line. Lines may come in
+//! any order after the header; unknown keys are rejected so a newer format isn't misread.
+
+use crate::Word;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const HEADER: &str = "intcode-snapshot";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub ip: Word,
+    pub base: Word,
+    pub halted: bool,
+    pub instructions: u64,
+    pub inputs: Vec<Word>,
+    pub outputs: Vec<Word>,
+    /// Allocated memory as `(start address, words)` regions.
+    pub memory: Vec<(usize, Vec<Word>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "snapshot line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for SnapshotError {}
+
+impl Snapshot {
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn load(path: &str) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The word at `address` in the saved memory, 0 if it isn't in a region.
+    pub fn word(&self, address: Word) -> Word {
+        let address = match usize::try_from(address) {
+            Ok(address) => address,
+            Err(_) => return 0,
+        };
+        self.memory
+            .iter()
+            .find_map(|(start, words)| words.get(address.checked_sub(*start)?))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Comma separated values with a leading space, or nothing for an empty list.
+fn join(values: &[Word]) -> String {
+    if values.is_empty() {
+        return String::new();
+    }
+    let values = values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    format!(" {}", values.join(","))
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, SNAPSHOT_VERSION)?;
+        writeln!(f, "ip {}", self.ip)?;
+        writeln!(f, "base {}", self.base)?;
+        writeln!(f, "halted {}", self.halted as u8)?;
+        writeln!(f, "instructions {}", self.instructions)?;
+        writeln!(f, "inputs{}", join(&self.inputs))?;
+        writeln!(f, "outputs{}", join(&self.outputs))?;
+        for (start, words) in &self.memory {
+            writeln!(f, "memory {}{}", start, join(words))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = SnapshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        let error = |line, message: String| SnapshotError { line, message };
+
+        match lines.next() {
+            Some((_, header)) if header == format!("{} {}", HEADER, SNAPSHOT_VERSION) => (),
+            Some((_, header)) if header.starts_with(HEADER) => {
+                return Err(error(1, format!("unsupported version: {}", header)))
+            }
+            _ => return Err(error(1, "not an intcode snapshot".to_string())),
+        }
+
+        let mut snapshot = Self::default();
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let mut parts = line.splitn(2, ' ');
+            let key = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("").trim();
+            let number = |text: &str| {
+                text.parse::<Word>()
+                    .map_err(|_| error(n, format!("invalid number: {:?}", text)))
+            };
+            let list = |text: &str| {
+                text.split(',')
+                    .filter(|v| !v.is_empty())
+                    .map(|v| number(v.trim()))
+                    .collect::<Result<Vec<_>, _>>()
+            };
+
+            match key {
+                "ip" => snapshot.ip = number(value)?,
+                "base" => snapshot.base = number(value)?,
+                "halted" => snapshot.halted = number(value)? != 0,
+                "instructions" => {
+                    snapshot.instructions = value
+                        .parse()
+                        .map_err(|_| error(n, format!("invalid count: {:?}", value)))?
+                }
+                "inputs" => snapshot.inputs = list(value)?,
+                "outputs" => snapshot.outputs = list(value)?,
+                "memory" => {
+                    let mut parts = value.splitn(2, ' ');
+                    let start = parts.next().unwrap_or("");
+                    let start: usize = start
+                        .parse()
+                        .map_err(|_| error(n, format!("invalid address: {:?}", start)))?;
+                    let words = list(parts.next().unwrap_or(""))?;
+                    let fits = start
+                        .checked_add(words.len())
+                        .is_some_and(|end| Word::try_from(end).is_ok());
+                    if !fits {
+                        return Err(error(
+                            n,
+                            "memory past the end of the address space".to_string(),
+                        ));
+                    }
+                    snapshot.memory.push((start, words));
+                }
+                _ => return Err(error(n, format!("unknown key: {:?}", key))),
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, Computer, IntcodeError, Limit, Limits, PagedMemory, Status};
+
+    #[test]
+    fn test_round_trip() {
+        let program = assemble(
+            "in [x]
+             mul [x], #10, [x]
+             out [x]
+             in [x]
+             hlt
+             x: .data 1",
+        )
+        .unwrap();
+        let mut computer = Computer::new(&program, &[2, 3]);
+        assert_eq!(computer.step(), Ok(Status::Output(20)));
+
+        let snapshot = computer.snapshot();
+        let text = snapshot.to_string();
+        assert_eq!(
+            text,
+            "intcode-snapshot 1
+ip 8
+base 0
+halted 0
+instructions 3
+inputs 3
+outputs 20
+memory 0 3,11,1002,11,10,11,4,11,3,11,99,20
+"
+        );
+        assert_eq!(text.parse(), Ok(snapshot.clone()));
+
+        let mut restored: Computer = Computer::restore(&snapshot);
+        assert_eq!(restored.run(), Ok(Status::Halted));
+        assert_eq!(restored.peek(11), 3);
+        assert_eq!(restored.outputs(), &[20]);
+        assert_eq!(restored.counters().instructions, 5);
+
+        let paged: Computer<PagedMemory> = Computer::restore(&snapshot);
+        assert_eq!(paged.snapshot().memory[0].1[..12], snapshot.memory[0].1[..]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            "intcode-snapshot 2\n"
+                .parse::<Snapshot>()
+                .unwrap_err()
+                .message,
+            "unsupported version: intcode-snapshot 2"
+        );
+        assert_eq!(
+            "intcode-snapshot 1\nip 0\nbase x\n".parse::<Snapshot>(),
+            Err(SnapshotError {
+                line: 3,
+                message: "invalid number: \"x\"".to_string()
+            })
+        );
+        assert_eq!(
+            "intcode-snapshot 1\nregisters 0\n"
+                .parse::<Snapshot>()
+                .unwrap_err()
+                .line,
+            2
+        );
+        assert_eq!(
+            "intcode-snapshot 1\nmemory 18446744073709551615 1,2\n"
+                .parse::<Snapshot>()
+                .unwrap_err()
+                .to_string(),
+            "snapshot line 2: memory past the end of the address space"
+        );
+    }
+
+    #[test]
+    fn test_untrusted_restore() {
+        let snapshot: Snapshot = "intcode-snapshot 1\nip 0\nmemory 0 99\nmemory 1000000000000 1\n"
+            .parse()
+            .unwrap();
+        assert_eq!(snapshot.word(0), 99);
+        match Computer::<Vec<Word>>::try_restore(&snapshot, Limits::none().memory(1 << 20)) {
+            Err(IntcodeError::LimitExceeded {
+                ip: 0,
+                instruction: 99,
+                limit: Limit::Memory,
+                counters,
+            }) => assert_eq!(counters.memory, 1_000_000_000_001),
+            Err(e) => panic!("unexpected {}", e),
+            Ok(_) => panic!("restored"),
+        }
+
+        let paged: Computer<PagedMemory> =
+            Computer::try_restore(&snapshot, Limits::none().memory(1 << 20)).unwrap();
+        assert_eq!(paged.peek(1_000_000_000_000), 1);
+        assert_eq!(paged.limits().memory, Some(1 << 20));
+
+        let overflowing = Snapshot {
+            memory: vec![(usize::MAX, vec![1, 2])],
+            ..Snapshot::default()
+        };
+        assert!(Computer::<Vec<Word>>::try_restore(&overflowing, Limits::none()).is_err());
+    }
+}
@@ -0,0 +1,539 @@
+//! Solving for unknown memory cells, like day 2's noun and verb.
+//!
+//! The program is first executed symbolically with the unknown cells as variables. As long as
+//! instruction words, addresses and jumps don't depend on them, the result cell ends up as an
+//! `Affine` expression of the unknowns that can be solved directly. Values that aren't affine,
+//! like products of unknowns, are only a problem once something depends on them. If one does,
+//! the solver falls back to running the program for every combination, on all cores.
+
+use crate::{Computer, Instruction, Limits, Mode, Op, Patch, Status, Word};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+
+/// `constant` plus the sum of every coefficient times the cell at its address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Affine {
+    pub constant: Word,
+    /// Coefficients by address, never zero.
+    pub terms: BTreeMap<usize, Word>,
+}
+
+impl Affine {
+    pub fn constant(value: Word) -> Self {
+        Self {
+            constant: value,
+            terms: BTreeMap::new(),
+        }
+    }
+
+    pub fn unknown(address: usize) -> Self {
+        let mut terms = BTreeMap::new();
+        terms.insert(address, 1);
+        Self { constant: 0, terms }
+    }
+
+    pub fn as_constant(&self) -> Option<Word> {
+        if self.terms.is_empty() {
+            Some(self.constant)
+        } else {
+            None
+        }
+    }
+
+    pub fn coefficient(&self, address: usize) -> Word {
+        self.terms.get(&address).copied().unwrap_or(0)
+    }
+
+    /// The value with the unknowns taken from `patch`. None if one is missing, or on overflow.
+    pub fn eval(&self, patch: &Patch) -> Option<Word> {
+        self.terms
+            .iter()
+            .try_fold(self.constant, |sum, (&address, &k)| {
+                sum.checked_add(k.checked_mul(patch.get(address)?)?)
+            })
+    }
+
+    fn add(&self, other: &Self) -> Option<Self> {
+        let mut sum = self.clone();
+        sum.constant = sum.constant.checked_add(other.constant)?;
+        for (&address, &k) in &other.terms {
+            let k = sum.coefficient(address).checked_add(k)?;
+            if k == 0 {
+                sum.terms.remove(&address);
+            } else {
+                sum.terms.insert(address, k);
+            }
+        }
+        Some(sum)
+    }
+
+    fn scale(&self, factor: Word) -> Option<Self> {
+        if factor == 0 {
+            return Some(Self::constant(0));
+        }
+        let mut terms = BTreeMap::new();
+        for (&address, &k) in &self.terms {
+            terms.insert(address, k.checked_mul(factor)?);
+        }
+        Some(Self {
+            constant: self.constant.checked_mul(factor)?,
+            terms,
+        })
+    }
+}
+
+/// Like `250000*[1] + [2] + 190687`.
+impl fmt::Display for Affine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut terms: Vec<_> = self
+            .terms
+            .iter()
+            .map(|(address, k)| match k {
+                1 => format!("[{}]", address),
+                _ => format!("{}*[{}]", k, address),
+            })
+            .collect();
+        if self.constant != 0 || terms.is_empty() {
+            terms.push(self.constant.to_string());
+        }
+        write!(f, "{}", terms.join(" + "))
+    }
+}
+
+/// Why symbolic execution gave up, at the instruction at `ip`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotAffine {
+    pub ip: Word,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for NotAffine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "not affine at {}: {}", self.ip, self.reason)
+    }
+}
+
+impl Error for NotAffine {}
+
+/// Finds values for unknown cells that make the program halt with a target in the result
+/// cell. Solutions are the first in lexicographic order of the unknowns, however found.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    program: Vec<Word>,
+    unknowns: Vec<(usize, RangeInclusive<Word>)>,
+    result: usize,
+    max_steps: u64,
+    threads: usize,
+}
+
+impl Solver {
+    /// No unknowns yet, with the result in cell 0.
+    pub fn new(program: &[Word]) -> Self {
+        Self {
+            program: program.to_vec(),
+            unknowns: Vec::new(),
+            result: 0,
+            max_steps: 1_000_000,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+
+    pub fn unknown(mut self, address: usize, range: RangeInclusive<Word>) -> Self {
+        self.unknowns.push((address, range));
+        self
+    }
+
+    pub fn result(mut self, address: usize) -> Self {
+        self.result = address;
+        self
+    }
+
+    /// Instructions per run, symbolic or not, before giving up on it.
+    pub fn max_steps(mut self, max: u64) -> Self {
+        self.max_steps = max;
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Solves the result's expression if it is affine, or searches every combination.
+    pub fn solve(&self, target: Word) -> Option<Patch> {
+        match self.expression() {
+            Ok(expression) => self.solve_affine(&expression, target),
+            Err(_) => self.brute_force(target),
+        }
+    }
+
+    /// The result cell at halt as an expression of the unknowns.
+    pub fn expression(&self) -> Result<Affine, NotAffine> {
+        let mut machine = Machine {
+            memory: self
+                .program
+                .iter()
+                .map(|&w| Some(Affine::constant(w)))
+                .collect(),
+            ip: 0,
+            base: 0,
+        };
+        for &(address, _) in &self.unknowns {
+            machine.store(address, Some(Affine::unknown(address)));
+        }
+        for _ in 0..self.max_steps {
+            if machine.step()? {
+                return machine
+                    .memory
+                    .get(self.result)
+                    .cloned()
+                    .map_or(Ok(Affine::constant(0)), |value| {
+                        value.ok_or(machine.fail("result isn't affine"))
+                    });
+            }
+        }
+        Err(machine.fail("step limit reached"))
+    }
+
+    /// Runs the program for every combination of the unknowns, split between threads.
+    pub fn brute_force(&self, target: Word) -> Option<Patch> {
+        let count = combinations(&self.unknowns);
+        let threads = (self.threads as u64).min(count).max(1);
+        let chunk = count.div_ceil(threads);
+        let first = AtomicU64::new(u64::MAX);
+
+        thread::scope(|scope| {
+            for t in 0..threads {
+                let first = &first;
+                scope.spawn(move || {
+                    let end = (t * chunk).saturating_add(chunk).min(count);
+                    for index in t * chunk..end {
+                        if index >= first.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        if self.check(&self.patch(index), target) {
+                            first.fetch_min(index, Ordering::Relaxed);
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        match first.into_inner() {
+            u64::MAX => None,
+            index => Some(self.patch(index)),
+        }
+    }
+
+    /// Solves for the last unknown with a coefficient, trying every combination of the ones
+    /// before it. Every candidate is checked with a real run.
+    fn solve_affine(&self, expression: &Affine, target: Word) -> Option<Patch> {
+        let solved = self
+            .unknowns
+            .iter()
+            .rposition(|(address, _)| expression.coefficient(*address) != 0);
+        let solved = match solved {
+            Some(solved) => solved,
+            None => {
+                let patch = self.patch(0);
+                return Some(patch).filter(|patch| self.check(patch, target));
+            }
+        };
+        let (address, range) = &self.unknowns[solved];
+        let k = expression.coefficient(*address);
+
+        // Unknowns after the solved one don't matter, so they stay at the start of their range.
+        let stride = combinations(&self.unknowns[solved..]);
+        for index in 0..combinations(&self.unknowns[..solved]) {
+            // Past the saturated count, and so are all the indices after it.
+            let mut patch = match index.checked_mul(stride) {
+                Some(index) => self.patch(index),
+                None => break,
+            };
+            patch.set(*address, 0);
+            let rest = match expression.eval(&patch).and_then(|v| target.checked_sub(v)) {
+                Some(rest) => rest,
+                None => continue,
+            };
+            let value = match (rest.checked_rem(k), rest.checked_div(k)) {
+                (Some(0), Some(value)) if range.contains(&value) => value,
+                _ => continue,
+            };
+            patch.set(*address, value);
+            if self.check(&patch, target) {
+                return Some(patch);
+            }
+        }
+        None
+    }
+
+    fn check(&self, patch: &Patch, target: Word) -> bool {
+        let mut computer = Computer::new(&self.program, &[]);
+        computer.set_limits(Limits::none().instructions(self.max_steps));
+        computer.patch(patch);
+        computer.run() == Ok(Status::Halted) && computer.peek(self.result) == target
+    }
+
+    /// The `index`th combination in lexicographic order.
+    fn patch(&self, mut index: u64) -> Patch {
+        let mut patch = Patch::new();
+        for (address, range) in self.unknowns.iter().rev() {
+            let len = len(range).max(1);
+            patch.set(*address, range.start().wrapping_add((index % len) as Word));
+            index /= len;
+        }
+        patch
+    }
+}
+
+/// Saturating.
+fn combinations(unknowns: &[(usize, RangeInclusive<Word>)]) -> u64 {
+    unknowns
+        .iter()
+        .fold(1, |count: u64, (_, range)| count.saturating_mul(len(range)))
+}
+
+fn len(range: &RangeInclusive<Word>) -> u64 {
+    range
+        .end()
+        .checked_sub(*range.start())
+        .filter(|&d| d >= 0)
+        .and_then(|d| u64::try_from(d).ok())
+        .map_or(0, |d| d.saturating_add(1))
+}
+
+/// `None` for values that depend on the unknowns, but not affinely.
+type Value = Option<Affine>;
+
+struct Machine {
+    memory: Vec<Value>,
+    ip: Word,
+    base: Word,
+}
+
+impl Machine {
+    const fn fail(&self, reason: &'static str) -> NotAffine {
+        NotAffine {
+            ip: self.ip,
+            reason,
+        }
+    }
+
+    fn load(&self, address: Word) -> Result<Value, NotAffine> {
+        let address = usize::try_from(address).map_err(|_| self.fail("negative address"))?;
+        Ok(match self.memory.get(address) {
+            Some(value) => value.clone(),
+            None => Some(Affine::constant(0)),
+        })
+    }
+
+    fn store(&mut self, address: usize, value: Value) {
+        if self.memory.len() <= address {
+            self.memory.resize(address + 1, Some(Affine::constant(0)));
+        }
+        self.memory[address] = value;
+    }
+
+    fn concrete(&self, value: Value, reason: &'static str) -> Result<Word, NotAffine> {
+        value
+            .as_ref()
+            .and_then(Affine::as_constant)
+            .ok_or(self.fail(reason))
+    }
+
+    /// Executes one instruction. True once halted.
+    fn step(&mut self) -> Result<bool, NotAffine> {
+        let word = self.concrete(self.load(self.ip)?, "code depends on the unknowns")?;
+        let instruction =
+            Instruction::try_from(word).map_err(|_| self.fail("illegal instruction"))?;
+        let op = instruction.op;
+
+        // Reading through an address that depends on the unknowns isn't a problem yet, it
+        // just gives a value that isn't affine. For the write parameter, this is the address.
+        let mut operands = Vec::new();
+        for i in 0..op.arity() {
+            let param = self.load(self.ip + 1 + i as Word)?;
+            let address = match instruction.modes[i] {
+                Mode::Immediate => {
+                    operands.push(param);
+                    continue;
+                }
+                Mode::Position => param,
+                Mode::Relative => param.and_then(|p| p.add(&Affine::constant(self.base))),
+            };
+            operands.push(if op.write_param() == Some(i) {
+                address
+            } else {
+                match address.as_ref().and_then(Affine::as_constant) {
+                    Some(address) => self.load(address)?,
+                    None => None,
+                }
+            });
+        }
+        let next = self.ip + 1 + op.arity() as Word;
+
+        let result = match op {
+            Op::Add => match (&operands[0], &operands[1]) {
+                (Some(x), Some(y)) => x.add(y),
+                _ => None,
+            },
+            Op::Multiply => match (&operands[0], &operands[1]) {
+                (Some(x), Some(y)) => match (x.as_constant(), y.as_constant()) {
+                    (Some(k), _) => y.scale(k),
+                    (_, Some(k)) => x.scale(k),
+                    _ => None,
+                },
+                _ => None,
+            },
+            Op::LessThan | Op::Equals => {
+                let constant = |value: &Value| value.as_ref().and_then(Affine::as_constant);
+                match (constant(&operands[0]), constant(&operands[1])) {
+                    (Some(x), Some(y)) if op == Op::LessThan => {
+                        Some(Affine::constant(Word::from(x < y)))
+                    }
+                    (Some(x), Some(y)) => Some(Affine::constant(Word::from(x == y))),
+                    _ => None,
+                }
+            }
+            Op::JumpIfTrue | Op::JumpIfFalse => {
+                let condition =
+                    self.concrete(operands[0].clone(), "jump depends on the unknowns")?;
+                self.ip = if (condition != 0) == (op == Op::JumpIfTrue) {
+                    self.concrete(operands[1].clone(), "jump depends on the unknowns")?
+                } else {
+                    next
+                };
+                return Ok(false);
+            }
+            Op::AdjustBase => {
+                let offset = self.concrete(operands[0].clone(), "base depends on the unknowns")?;
+                self.base = self
+                    .base
+                    .checked_add(offset)
+                    .ok_or(self.fail("base overflow"))?;
+                self.ip = next;
+                return Ok(false);
+            }
+            Op::Read | Op::Write => return Err(self.fail("input and output aren't supported")),
+            Op::Halt => return Ok(true),
+            Op::Extended { .. } => return Err(self.fail("extended instruction")),
+        };
+
+        if instruction.modes[2] == Mode::Immediate {
+            return Err(self.fail("write to an immediate"));
+        }
+        let target = self.concrete(operands[2].clone(), "write depends on the unknowns")?;
+        let target = usize::try_from(target).map_err(|_| self.fail("negative address"))?;
+        self.store(target, result);
+        self.ip = next;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble, load_intcode};
+
+    #[test]
+    fn test_day02() {
+        let program = load_intcode("../day02/input/input.txt");
+        let solver = Solver::new(&program).unknown(1, 0..=99).unknown(2, 0..=99);
+        let expression = solver.expression().unwrap();
+        assert_eq!(expression.coefficient(2), 1);
+        assert_eq!(expression.terms.len(), 2);
+        assert_eq!(expression.eval(&Patch::noun_verb(12, 2)), Some(3_654_868));
+
+        let patch = solver.solve(19_690_720).unwrap();
+        assert_eq!(patch, Patch::noun_verb(70, 14));
+        assert_eq!(solver.brute_force(19_690_720), Some(patch));
+        assert_eq!(solver.solve(-1), None);
+    }
+
+    #[test]
+    fn test_affine() {
+        let x = Affine::unknown(1).scale(3).unwrap();
+        let y = Affine::unknown(2).add(&Affine::constant(-4)).unwrap();
+        let sum = x.add(&y).unwrap();
+        assert_eq!(sum.to_string(), "3*[1] + [2] + -4");
+        assert_eq!(sum.eval(&Patch::noun_verb(2, 5)), Some(7));
+        assert_eq!(sum.eval(&Patch::new().with(1, 2)), None);
+        assert_eq!(sum.add(&y.scale(-1).unwrap()), Some(x));
+        assert_eq!(Affine::constant(0).to_string(), "0");
+    }
+
+    #[test]
+    fn test_fallback() {
+        // x * y, with both zeroed before halting unless x + y < 9.
+        let program = assemble(
+            "
+                    mul [x], [y], [0]
+                    add [x], [y], [s]
+                    lt [s], #9, [s]
+                    jt [s], #done
+                    add #0, #0, [0]
+            done:   hlt
+            x:      .data 0
+            y:      .data 0
+            s:      .data 0",
+        )
+        .unwrap();
+        let x = program.len() - 3;
+        let solver = Solver::new(&program)
+            .unknown(x, 0..=9)
+            .unknown(x + 1, 0..=9)
+            .threads(3);
+        assert_eq!(
+            solver.expression().unwrap_err().reason,
+            "jump depends on the unknowns"
+        );
+        assert_eq!(
+            solver.solve(12),
+            Some(Patch::new().with(x, 2).with(x + 1, 6))
+        );
+        assert_eq!(solver.solve(18), None);
+        assert_eq!(
+            solver.solve(0),
+            Some(Patch::new().with(x, 0).with(x + 1, 0))
+        );
+    }
+
+    #[test]
+    fn test_unused_product() {
+        // [10] = x * x, [0] = x + x
+        let program = [2, 9, 9, 10, 1, 9, 9, 0, 99, 0, 0];
+        let solver = Solver::new(&program).unknown(9, 0..=9);
+        assert_eq!(solver.expression().unwrap().to_string(), "2*[9]");
+        assert_eq!(solver.solve(14), Some(Patch::new().with(9, 7)));
+        assert_eq!(solver.solve(7), None);
+
+        let solver = solver.result(10);
+        assert_eq!(
+            solver.expression().unwrap_err().to_string(),
+            "not affine at 8: result isn't affine"
+        );
+        assert_eq!(solver.solve(49), Some(Patch::new().with(9, 7)));
+    }
+
+    #[test]
+    fn test_overflowing_search() {
+        // [0] = -x
+        let program = assemble("mul [x], #-1, [0]\nhlt\nx: .data 0").unwrap();
+        let solver = Solver::new(&program).unknown(5, 0..=9);
+        assert_eq!(solver.solve(-3), Some(Patch::new().with(5, 3)));
+        assert_eq!(solver.solve(Word::MIN), None);
+
+        // [0] = y, with the stride over y's range overflowing after two values of x.
+        let program = assemble("add [y], #0, [0]\nhlt\nx: .data 0\ny: .data 0").unwrap();
+        let solver = Solver::new(&program)
+            .unknown(5, 0..=9)
+            .unknown(6, 0..=Word::MAX);
+        assert_eq!(solver.solve(-1), None);
+    }
+}
@@ -0,0 +1,226 @@
+//! Computers on their own threads, wired together with channels.
+//!
+//! Every node reads from a single channel and copies each output to every node it is connected
+//! to, so chains, rings, fan-out and fan-in are all just `connect` calls. A node stops once it
+//! halts, fails, or waits for input after every sender to its channel is gone.
+
+use crate::{Computer, IntcodeError, OutputSink, Status, Word};
+use std::panic;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+pub type ThreadedComputer = Computer<Vec<Word>, Receiver<Word>, Fanout>;
+
+/// Sends every output to each target and keeps a copy.
+#[derive(Debug, Default)]
+pub struct Fanout {
+    targets: Vec<Sender<Word>>,
+    values: Vec<Word>,
+}
+
+impl Fanout {
+    pub fn values(&self) -> &[Word] {
+        &self.values
+    }
+
+    /// Drops the senders so downstream nodes can see that nothing more is coming.
+    fn close(&mut self) {
+        self.targets.clear();
+    }
+}
+
+impl OutputSink for Fanout {
+    fn emit(&mut self, value: Word) {
+        for target in &self.targets {
+            target.send(value).ok();
+        }
+        self.values.push(value);
+    }
+}
+
+struct Node {
+    program: Vec<Word>,
+    sender: Sender<Word>,
+    receiver: Receiver<Word>,
+    targets: Vec<Sender<Word>>,
+}
+
+#[derive(Default)]
+pub struct Pipeline {
+    nodes: Vec<Node>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a node with `inputs` already queued and returns its id.
+    pub fn add(&mut self, program: &[Word], inputs: &[Word]) -> usize {
+        let (sender, receiver) = mpsc::channel();
+        for &input in inputs {
+            sender.send(input).ok();
+        }
+        self.nodes.push(Node {
+            program: program.to_vec(),
+            sender,
+            receiver,
+            targets: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    pub fn connect(&mut self, from: usize, to: usize) -> &mut Self {
+        let sender = self.nodes[to].sender.clone();
+        self.nodes[from].targets.push(sender);
+        self
+    }
+
+    pub fn chain(&mut self, ids: &[usize]) -> &mut Self {
+        for pair in ids.windows(2) {
+            self.connect(pair[0], pair[1]);
+        }
+        self
+    }
+
+    /// A chain whose last node feeds back into the first. It only stops once a node halts or
+    /// fails, see `Running::join`.
+    pub fn ring(&mut self, ids: &[usize]) -> &mut Self {
+        self.chain(ids);
+        if let (Some(&first), Some(&last)) = (ids.first(), ids.last()) {
+            self.connect(last, first);
+        }
+        self
+    }
+
+    /// A sender for feeding `id` from outside. The node keeps waiting for input for as long as
+    /// it is alive.
+    pub fn input(&self, id: usize) -> Sender<Word> {
+        self.nodes[id].sender.clone()
+    }
+
+    /// A receiver for the outputs of `id`.
+    pub fn output(&mut self, id: usize) -> Receiver<Word> {
+        let (sender, receiver) = mpsc::channel();
+        self.nodes[id].targets.push(sender);
+        receiver
+    }
+
+    pub fn spawn(self) -> Running {
+        let handles = self
+            .nodes
+            .into_iter()
+            .map(|node| {
+                drop(node.sender);
+                let outputs = Fanout {
+                    targets: node.targets,
+                    values: Vec::new(),
+                };
+                let mut computer = Computer::with_io(node.program, node.receiver, outputs);
+                thread::spawn(move || {
+                    let result = computer.run();
+                    computer.output_mut().close();
+                    Finished { computer, result }
+                })
+            })
+            .collect();
+        Running { handles }
+    }
+}
+
+pub struct Running {
+    handles: Vec<JoinHandle<Finished>>,
+}
+
+impl Running {
+    /// Waits for every node, in id order. A panic on a node thread is resumed here.
+    ///
+    /// Never returns while a node blocks on input that can't come: in a ring where every node
+    /// waits for input, each one holds the sender the next is reading from, so none of them
+    /// ever sees its input end. Such rings have to halt on their own, or be fed through a
+    /// sender from `Pipeline::input`.
+    pub fn join(self) -> Vec<Finished> {
+        self.handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    }
+}
+
+pub struct Finished {
+    pub computer: ThreadedComputer,
+    /// `Ok(Status::NeedsInput)` means the node ran out of input for good.
+    pub result: Result<Status, IntcodeError>,
+}
+
+impl Finished {
+    pub fn outputs(&self) -> &[Word] {
+        self.computer.output().values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    const AMPLIFIER: &[Word] = &[
+        3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1, 28,
+        1005, 28, 6, 99, 0, 0, 5,
+    ];
+
+    #[test]
+    fn test_ring() {
+        let mut pipeline = Pipeline::new();
+        let ids: Vec<_> = [9, 8, 7, 6, 5]
+            .iter()
+            .enumerate()
+            .map(|(i, &phase)| {
+                let inputs = if i == 0 { vec![phase, 0] } else { vec![phase] };
+                pipeline.add(AMPLIFIER, &inputs)
+            })
+            .collect();
+        pipeline.ring(&ids);
+
+        let finished = pipeline.spawn().join();
+        assert!(finished.iter().all(|f| f.result == Ok(Status::Halted)));
+        assert_eq!(finished[4].outputs().last(), Some(&139_629_729));
+    }
+
+    #[test]
+    fn test_chain_and_fan_out() {
+        let doubler = assemble(
+            "loop:   in [x]
+                     mul [x], #2, [x]
+                     out [x]
+                     jt #1, #loop
+             x:      .data 0",
+        )
+        .unwrap();
+        let mut pipeline = Pipeline::new();
+        let first = pipeline.add(&doubler, &[]);
+        let second = pipeline.add(&doubler, &[]);
+        let third = pipeline.add(&doubler, &[]);
+        let broken = pipeline.add(&[42], &[]);
+        pipeline.chain(&[first, second]).connect(first, third);
+        let input = pipeline.input(first);
+        let output = pipeline.output(second);
+
+        let running = pipeline.spawn();
+        input.send(1).unwrap();
+        input.send(5).unwrap();
+        drop(input);
+        assert_eq!(output.iter().collect::<Vec<_>>(), vec![4, 20]);
+
+        let finished = running.join();
+        assert_eq!(finished[first].result, Ok(Status::NeedsInput));
+        assert_eq!(finished[third].outputs(), &[4, 20]);
+        assert_eq!(
+            finished[broken].result,
+            Err(IntcodeError::IllegalOpcode {
+                ip: 0,
+                instruction: 42
+            })
+        );
+    }
+}
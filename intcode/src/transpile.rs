@@ -0,0 +1,193 @@
+//! Translates a program into a specialized Rust function.
+//!
+//! Every instruction found by `Analysis` becomes one arm of a `match` on the instruction
+//! pointer, with its operands baked in. The generated code only ever runs instructions it was
+//! generated from: whenever it lands on an unknown address, is about to write into one of
+//! those instructions, needs an input that isn't there, would touch a negative address or
+//! would overflow, it hands its state to the interpreter through a `Snapshot` and lets
+//! `Computer::run` take it from there. So self-modifying programs still run correctly, just
+//! without the speedup past the first modification.
+
+use crate::{Analysis, Mode, Op, Operand, Word};
+use std::fmt::Write;
+
+fn read(operand: &Operand) -> Option<String> {
+    let value = operand.value;
+    match operand.mode {
+        Mode::Immediate => Some(value.to_string()),
+        Mode::Position if value < 0 => None,
+        Mode::Position => Some(format!("memory.get({}).copied().unwrap_or(0)", value)),
+        Mode::Relative => Some(format!(
+            "{{ let a = match base.checked_add({}) {{ Some(a) if a >= 0 => a, _ => break false }}; \
+             memory.get(a as usize).copied().unwrap_or(0) }}",
+            value
+        )),
+    }
+}
+
+fn target(operand: &Operand, ranges: &[(usize, usize)]) -> Option<String> {
+    let value = operand.value;
+    match operand.mode {
+        Mode::Immediate => None,
+        Mode::Position if value < 0 => None,
+        Mode::Position => {
+            let address = value as usize;
+            if ranges.iter().any(|&(s, e)| (s..=e).contains(&address)) {
+                None
+            } else {
+                Some(format!("let t = {}usize;", address))
+            }
+        }
+        Mode::Relative => Some(format!(
+            "let t = match base.checked_add({}) {{ Some(t) if t >= 0 && !is_code(t) => t as usize, \
+             _ => break false }};",
+            value
+        )),
+    }
+}
+
+/// Falls back on overflow, so the interpreter reports it.
+fn checked(value: &str, then: &str) -> String {
+    format!(
+        "let v = match {} {{ Some(v) => v, None => break false }}; {}",
+        value, then
+    )
+}
+
+/// The body of the match arm for one instruction, or `None` if it can only ever fall back.
+fn arm(address: usize, op: Op, operands: &[Operand], ranges: &[(usize, usize)]) -> Option<String> {
+    let mut body = String::new();
+    for (i, operand) in operands.iter().enumerate() {
+        if op.write_param() == Some(i) {
+            body += &target(operand, ranges)?;
+        } else {
+            write!(body, "let p{}: Word = {};", i, read(operand)?).unwrap();
+        }
+    }
+
+    let next = address + operands.len() + 1;
+    let effect = match op {
+        Op::Add => checked("p0.checked_add(p1)", "store(&mut memory, t, v);"),
+        Op::Multiply => checked("p0.checked_mul(p1)", "store(&mut memory, t, v);"),
+        Op::Read => "let v = match inputs.pop_front() { Some(v) => v, None => break false }; \
+                     store(&mut memory, t, v);"
+            .to_string(),
+        Op::Write => "outputs.push(p0);".to_string(),
+        Op::JumpIfTrue => format!("ip = if p0 != 0 {{ p1 }} else {{ {} }};", next),
+        Op::JumpIfFalse => format!("ip = if p0 == 0 {{ p1 }} else {{ {} }};", next),
+        Op::LessThan => "store(&mut memory, t, (p0 < p1) as Word);".to_string(),
+        Op::Equals => "store(&mut memory, t, (p0 == p1) as Word);".to_string(),
+        Op::AdjustBase => checked("base.checked_add(p0)", "base = v;"),
+        Op::Halt => return Some("executed += 1; break true;".to_string()),
+        Op::Extended { .. } => return None,
+    };
+    body += &effect;
+    if op != Op::JumpIfTrue && op != Op::JumpIfFalse {
+        write!(body, "ip = {};", next).unwrap();
+    }
+    body += "executed += 1;";
+    Some(body)
+}
+
+/// Rust source for `fn <name>(inputs: &[Word]) -> Result<intcode::Computer, intcode::IntcodeError>`,
+/// which behaves like `Computer::new(program, inputs)` followed by `run`.
+pub fn transpile(program: &[Word], name: &str) -> String {
+    let analysis = Analysis::new(program);
+    let instructions = analysis.instructions();
+    let ranges = analysis.code_ranges();
+
+    let mut arms = String::new();
+    for (&address, (op, operands)) in instructions {
+        if let Some(body) = arm(address, *op, operands, &ranges) {
+            writeln!(arms, "            {} => {{ {} }}", address, body).unwrap();
+        }
+    }
+    let is_code = if ranges.is_empty() {
+        "false".to_string()
+    } else {
+        let ranges = ranges
+            .iter()
+            .map(|(start, end)| format!("{}..={}", start, end))
+            .collect::<Vec<_>>();
+        format!("matches!(address, {})", ranges.join(" | "))
+    };
+    let memory = program
+        .iter()
+        .map(|w| w.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "#[allow(clippy::all, unused)]
+fn {name}(inputs: &[::intcode::Word]) -> Result<::intcode::Computer, ::intcode::IntcodeError> {{
+    use ::intcode::Word;
+
+    fn is_code(address: Word) -> bool {{
+        {is_code}
+    }}
+    fn store(memory: &mut Vec<Word>, address: usize, value: Word) {{
+        if memory.len() <= address {{
+            memory.resize(address + 1, 0);
+        }}
+        memory[address] = value;
+    }}
+
+    let mut memory: Vec<Word> = vec![{memory}];
+    let mut inputs: ::std::collections::VecDeque<Word> = inputs.iter().cloned().collect();
+    let mut outputs: Vec<Word> = Vec::new();
+    let mut ip: Word = 0;
+    let mut base: Word = 0;
+    let mut executed: u64 = 0;
+
+    let halted = loop {{
+        match ip {{
+{arms}            _ => break false,
+        }}
+    }};
+
+    let snapshot = ::intcode::Snapshot {{
+        ip,
+        base,
+        halted,
+        instructions: executed,
+        inputs: inputs.into_iter().collect(),
+        outputs,
+        memory: vec![(0, memory)],
+    }};
+    let mut computer: ::intcode::Computer = ::intcode::Computer::restore(&snapshot);
+    if !halted {{
+        computer.run()?;
+    }}
+    Ok(computer)
+}}
+",
+        name = name,
+        is_code = is_code,
+        memory = memory,
+        arms = arms,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assemble;
+
+    #[test]
+    fn test_writes_into_code_fall_back() {
+        let program = assemble(
+            "code:  add #1, #2, [code]
+                    add #1, #2, [x]
+                    add #1, #2, rb+0
+                    hlt
+             x:     .data 0",
+        )
+        .unwrap();
+        let source = transpile(&program, "program");
+        assert!(source.contains("fn program(inputs: &[::intcode::Word])"));
+        assert!(!source.contains("0 => {"));
+        assert!(source.contains("4 => { let p0: Word = 1;let p1: Word = 2;let t = 13usize;"));
+        assert!(source.contains("!is_code(t)"));
+        assert!(source.contains("matches!(address, 0..=12)"));
+    }
+}
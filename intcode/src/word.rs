@@ -0,0 +1,33 @@
+/// The value of a memory cell. `i64` by default, `i128` with the `i128` feature.
+#[cfg(not(feature = "i128"))]
+pub type Word = i64;
+#[cfg(feature = "i128")]
+pub type Word = i128;
+
+/// What `add`, `mul` and `arb` do when the result doesn't fit in a `Word`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Arithmetic {
+    /// Fail with `IntcodeError::Overflow`, without executing the instruction.
+    #[default]
+    Checked,
+    /// Wrap around, two's complement style.
+    Wrapping,
+}
+
+impl Arithmetic {
+    #[inline]
+    pub fn add(self, x: Word, y: Word) -> Option<Word> {
+        match self {
+            Self::Checked => x.checked_add(y),
+            Self::Wrapping => Some(x.wrapping_add(y)),
+        }
+    }
+
+    #[inline]
+    pub fn mul(self, x: Word, y: Word) -> Option<Word> {
+        match self {
+            Self::Checked => x.checked_mul(y),
+            Self::Wrapping => Some(x.wrapping_mul(y)),
+        }
+    }
+}